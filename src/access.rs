@@ -0,0 +1,159 @@
+//! Access modes for app credentials and how they map onto provider permissions.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What an app may do inside its folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessMode {
+    /// List and download objects
+    Read,
+    /// Upload and delete objects, but not read them back
+    Write,
+    /// Upload objects only - nothing can be read or deleted.
+    /// Uploads can still overwrite existing objects under the same key.
+    Append,
+    /// Read, upload and delete objects
+    #[default]
+    Full,
+}
+
+impl AccessMode {
+    /// Whether the mode allows reading objects.
+    pub fn can_read(self) -> bool {
+        matches!(self, AccessMode::Read | AccessMode::Full)
+    }
+
+    /// Whether the mode allows uploading objects.
+    pub fn can_write(self) -> bool {
+        !matches!(self, AccessMode::Read)
+    }
+
+    /// Whether the mode allows deleting objects.
+    pub fn can_delete(self) -> bool {
+        matches!(self, AccessMode::Write | AccessMode::Full)
+    }
+
+    /// This mode without uploads and deletes, if anything is left.
+    pub fn read_only(self) -> Option<AccessMode> {
+        self.can_read().then_some(AccessMode::Read)
+    }
+
+    /// Exoscale SOS operations allowed under the app's prefix.
+    pub fn exoscale_operations(self) -> Vec<&'static str> {
+        let mut ops = Vec::new();
+        if self.can_read() {
+            ops.extend(["list-objects", "get-object", "head-object"]);
+        }
+        if self.can_write() {
+            ops.push("put-object");
+        }
+        if self.can_delete() {
+            ops.push("delete-object");
+        }
+        ops
+    }
+
+    /// Scaleway IAM permission sets granted on the project.
+    pub fn scaleway_permission_sets(self) -> Vec<&'static str> {
+        let mut sets = Vec::new();
+        if self.can_read() {
+            sets.push("ObjectStorageObjectsRead");
+        }
+        if self.can_write() {
+            sets.push("ObjectStorageObjectsWrite");
+        }
+        if self.can_delete() {
+            sets.push("ObjectStorageObjectsDelete");
+        }
+        sets
+    }
+
+    /// S3 bucket-policy actions allowed under the app's prefix.
+    pub fn bucket_policy_actions(self) -> Vec<&'static str> {
+        let mut actions = Vec::new();
+        if self.can_read() {
+            actions.push("s3:GetObject");
+        }
+        if self.can_write() {
+            actions.push("s3:PutObject");
+        }
+        if self.can_delete() {
+            actions.push("s3:DeleteObject");
+        }
+        actions
+    }
+}
+
+impl fmt::Display for AccessMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AccessMode::Read => "read",
+            AccessMode::Write => "write",
+            AccessMode::Append => "append",
+            AccessMode::Full => "full",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities() {
+        assert!(AccessMode::Read.can_read() && !AccessMode::Read.can_write() && !AccessMode::Read.can_delete());
+        assert!(!AccessMode::Write.can_read() && AccessMode::Write.can_write() && AccessMode::Write.can_delete());
+        assert!(!AccessMode::Append.can_read() && AccessMode::Append.can_write() && !AccessMode::Append.can_delete());
+        assert!(AccessMode::Full.can_read() && AccessMode::Full.can_write() && AccessMode::Full.can_delete());
+    }
+
+    #[test]
+    fn read_only() {
+        assert_eq!(AccessMode::Full.read_only(), Some(AccessMode::Read));
+        assert_eq!(AccessMode::Read.read_only(), Some(AccessMode::Read));
+        assert_eq!(AccessMode::Write.read_only(), None);
+        assert_eq!(AccessMode::Append.read_only(), None);
+    }
+
+    #[test]
+    fn exoscale_operations() {
+        assert_eq!(AccessMode::Read.exoscale_operations(), ["list-objects", "get-object", "head-object"]);
+        assert_eq!(AccessMode::Write.exoscale_operations(), ["put-object", "delete-object"]);
+        assert_eq!(AccessMode::Append.exoscale_operations(), ["put-object"]);
+        assert_eq!(
+            AccessMode::Full.exoscale_operations(),
+            ["list-objects", "get-object", "head-object", "put-object", "delete-object"]
+        );
+    }
+
+    #[test]
+    fn scaleway_permission_sets() {
+        assert_eq!(AccessMode::Read.scaleway_permission_sets(), ["ObjectStorageObjectsRead"]);
+        assert_eq!(AccessMode::Append.scaleway_permission_sets(), ["ObjectStorageObjectsWrite"]);
+        assert_eq!(
+            AccessMode::Full.scaleway_permission_sets(),
+            ["ObjectStorageObjectsRead", "ObjectStorageObjectsWrite", "ObjectStorageObjectsDelete"]
+        );
+    }
+
+    #[test]
+    fn bucket_policy_actions() {
+        assert_eq!(AccessMode::Read.bucket_policy_actions(), ["s3:GetObject"]);
+        assert_eq!(AccessMode::Write.bucket_policy_actions(), ["s3:PutObject", "s3:DeleteObject"]);
+        assert_eq!(AccessMode::Append.bucket_policy_actions(), ["s3:PutObject"]);
+        assert_eq!(
+            AccessMode::Full.bucket_policy_actions(),
+            ["s3:GetObject", "s3:PutObject", "s3:DeleteObject"]
+        );
+    }
+
+    #[test]
+    fn display_matches_serde() {
+        for mode in [AccessMode::Read, AccessMode::Write, AccessMode::Append, AccessMode::Full] {
+            assert_eq!(serde_json::to_value(mode).unwrap(), mode.to_string());
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Portable app archives: a zstd-compressed tar of an app's objects plus a manifest.
+//!
+//! Objects are stored as `data/<n>`, the n-th entry of the manifest, which holds
+//! their keys relative to the app's prefix, so an archive can be imported into any
+//! app. Tar would rewrite keys such as `folder/` or `a//b` if they were used as
+//! entry paths. `manifest.json` is written last, once the metadata of every object
+//! is known.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Archive layout version, bumped on incompatible changes.
+/// Version 1 stored objects under `data/<key>`; it can still be imported.
+pub const FORMAT_VERSION: u32 = 2;
+
+const MANIFEST_PATH: &str = "manifest.json";
+const DATA_DIR: &str = "data/";
+
+/// Describes the contents of an archive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+
+    /// App the archive was exported from
+    pub app: String,
+
+    /// Prefix the object keys are relative to
+    pub prefix: String,
+
+    /// Export time (RFC 3339)
+    pub exported_at: String,
+
+    pub objects: Vec<ManifestEntry>,
+}
+
+/// One object in an archive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Key relative to the app's prefix
+    pub key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub content_type: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Writes an archive one object at a time.
+pub struct ArchiveWriter {
+    builder: tar::Builder<zstd::Encoder<'static, File>>,
+}
+
+impl ArchiveWriter {
+    /// Create (or overwrite) the archive file at `path`.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        // Level 0 selects zstd's default level
+        let encoder = zstd::Encoder::new(file, 0).context("Failed to start compression")?;
+        Ok(Self {
+            builder: tar::Builder::new(encoder),
+        })
+    }
+
+    /// Add the content of the object that will be entry `index` of the manifest.
+    pub fn append_object(&mut self, index: usize, size: u64, data: impl Read) -> Result<()> {
+        let mut header = file_header(size);
+        self.builder
+            .append_data(&mut header, format!("{}{}", DATA_DIR, index), data)
+            .with_context(|| format!("Failed to add object {} to archive", index))
+    }
+
+    /// Write the manifest and flush the archive to disk.
+    pub fn finish(mut self, manifest: &Manifest) -> Result<()> {
+        let json = serde_json::to_vec_pretty(manifest)?;
+        let mut header = file_header(json.len() as u64);
+        self.builder
+            .append_data(&mut header, MANIFEST_PATH, json.as_slice())
+            .context("Failed to add manifest to archive")?;
+
+        let encoder = self.builder.into_inner().context("Failed to write archive")?;
+        encoder.finish().context("Failed to finish compression")?;
+        Ok(())
+    }
+}
+
+fn file_header(size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header
+}
+
+/// Open an archive for reading its entries in order.
+pub fn open(path: &Path) -> Result<tar::Archive<impl Read>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let decoder = zstd::Decoder::new(BufReader::new(file)).context("Failed to start decompression")?;
+    Ok(tar::Archive::new(decoder))
+}
+
+/// Read the manifest of an archive.
+pub fn read_manifest(path: &Path) -> Result<Manifest> {
+    let mut archive = open(path)?;
+    for entry in archive.entries().context("Failed to read archive")? {
+        let mut entry = entry.context("Failed to read archive")?;
+        if entry.path_bytes().as_ref() != MANIFEST_PATH.as_bytes() {
+            continue;
+        }
+
+        let manifest: Manifest = serde_json::from_reader(&mut entry).context("Invalid archive manifest")?;
+        if !(1..=FORMAT_VERSION).contains(&manifest.format) {
+            anyhow::bail!(
+                "Archive format {} is not supported (expected {}). Upgrade proprion to import it.",
+                manifest.format,
+                FORMAT_VERSION
+            );
+        }
+        // Keys end up in request URLs, where `..` would climb out of the app's folder
+        if let Some(object) = manifest
+            .objects
+            .iter()
+            .find(|o| o.key.starts_with('/') || !is_addressable_key(&o.key))
+        {
+            anyhow::bail!(
+                "Archive lists '{}', which would land outside the app's folder. Refusing to import it.",
+                object.key
+            );
+        }
+        return Ok(manifest);
+    }
+
+    anyhow::bail!("{} has no manifest - is it a proprion archive?", path.display())
+}
+
+/// Whether an object key can be used in S3 requests: URLs resolve `.` and `..`
+/// segments, so such keys would address another object.
+pub fn is_addressable_key(key: &str) -> bool {
+    !key.split('/').any(|part| part == "." || part == "..")
+}
+
+/// The index in `manifest.objects` of the object an entry holds, or `None` for
+/// entries that are not objects, like the manifest.
+pub fn object_index<R: Read>(entry: &tar::Entry<R>, manifest: &Manifest) -> Result<Option<usize>> {
+    let path = String::from_utf8(entry.path_bytes().into_owned()).context("Archive contains a non UTF-8 path")?;
+    let Some(name) = path.strip_prefix(DATA_DIR) else {
+        return Ok(None);
+    };
+
+    let index = if manifest.format == 1 {
+        manifest.objects.iter().position(|o| o.key == name)
+    } else {
+        name.parse().ok().filter(|index| *index < manifest.objects.len())
+    };
+    index
+        .map(Some)
+        .with_context(|| format!("'{}' is in the archive but not in its manifest", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(format: u32, keys: &[&str]) -> Manifest {
+        Manifest {
+            format,
+            app: "app".to_string(),
+            prefix: "apps/app/".to_string(),
+            exported_at: "2025-01-01T00:00:00Z".to_string(),
+            objects: keys
+                .iter()
+                .map(|key| ManifestEntry {
+                    key: key.to_string(),
+                    size: key.len() as u64,
+                    etag: None,
+                    content_type: None,
+                    last_modified: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// (key, content) of every object in the archive, in entry order
+    fn read_objects(path: &Path) -> Result<Vec<(String, String)>> {
+        let manifest = read_manifest(path)?;
+        let mut archive = open(path)?;
+        let mut objects = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if let Some(index) = object_index(&entry, &manifest)? {
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                objects.push((manifest.objects[index].key.clone(), content));
+            }
+        }
+        Ok(objects)
+    }
+
+    #[test]
+    fn keys_survive_a_round_trip_exactly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.tar.zst");
+        let keys = ["folder/", "a//b", "plain.txt"];
+
+        let mut writer = ArchiveWriter::create(&path).unwrap();
+        for (index, key) in keys.iter().enumerate() {
+            writer.append_object(index, key.len() as u64, key.as_bytes()).unwrap();
+        }
+        writer.finish(&manifest(FORMAT_VERSION, &keys)).unwrap();
+
+        let objects = read_objects(&path).unwrap();
+        let expected: Vec<(String, String)> = keys.iter().map(|k| (k.to_string(), k.to_string())).collect();
+        assert_eq!(objects, expected);
+    }
+
+    #[test]
+    fn version_1_archives_are_read_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.tar.zst");
+
+        let encoder = zstd::Encoder::new(File::create(&path).unwrap(), 0).unwrap();
+        let mut builder = tar::Builder::new(encoder);
+        builder.append_data(&mut file_header(5), "data/notes/a.txt", "hello".as_bytes()).unwrap();
+        let json = serde_json::to_vec(&manifest(1, &["notes/a.txt"])).unwrap();
+        builder.append_data(&mut file_header(json.len() as u64), MANIFEST_PATH, json.as_slice()).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let objects = read_objects(&path).unwrap();
+        assert_eq!(objects, [("notes/a.txt".to_string(), "hello".to_string())]);
+    }
+
+    #[test]
+    fn entries_missing_from_the_manifest_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.tar.zst");
+
+        let mut writer = ArchiveWriter::create(&path).unwrap();
+        writer.append_object(1, 1, "x".as_bytes()).unwrap();
+        writer.finish(&manifest(FORMAT_VERSION, &["only"])).unwrap();
+
+        let err = read_objects(&path).unwrap_err();
+        assert!(err.to_string().contains("'data/1' is in the archive but not in its manifest"));
+    }
+
+    #[test]
+    fn keys_outside_the_folder_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        for key in ["../other/x", "a/./b", "/root"] {
+            let path = dir.path().join("app.tar.zst");
+            let mut writer = ArchiveWriter::create(&path).unwrap();
+            writer.append_object(0, 1, "x".as_bytes()).unwrap();
+            writer.finish(&manifest(FORMAT_VERSION, &[key])).unwrap();
+
+            let err = read_manifest(&path).unwrap_err();
+            assert!(err.to_string().contains(key), "{}", err);
+        }
+    }
+
+    #[test]
+    fn newer_formats_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.tar.zst");
+        ArchiveWriter::create(&path).unwrap().finish(&manifest(FORMAT_VERSION + 1, &[])).unwrap();
+
+        assert!(read_manifest(&path).is_err());
+    }
+}
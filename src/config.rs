@@ -2,17 +2,33 @@
 //!
 //! Config file location: ~/.config/proprion/config.toml
 
+use crate::access::AccessMode;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Label of the key issued by `create-app`
+pub const DEFAULT_KEY_LABEL: &str = "default";
+
+/// Scaleway regions offering Object Storage
+pub const SCALEWAY_REGIONS: &[&str] = &["fr-par", "nl-ams", "pl-waw"];
+
+/// Exoscale zones offering Simple Object Storage
+pub const EXOSCALE_ZONES: &[&str] = &[
+    "ch-gva-2", "ch-dk-2", "de-fra-1", "de-muc-1", "at-vie-1", "at-vie-2", "bg-sof-1",
+];
 
 /// Main configuration structure
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
     pub providers: HashMap<String, ProviderConfig>,
+
+    #[serde(default)]
+    pub apps: HashMap<String, AppConfig>,
 }
 
 /// Provider configuration - different fields for different provider types
@@ -35,6 +51,9 @@ pub struct ScalewayProviderConfig {
     pub project_id: String,
     pub region: String,
     pub bucket: String,
+    /// S3 endpoint to use instead of the region's, e.g. a local S3 stand-in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
 }
 
 /// Exoscale-specific configuration
@@ -48,6 +67,113 @@ pub struct ExoscaleProviderConfig {
     pub zone: String,
     /// Bucket name
     pub bucket: String,
+    /// S3 endpoint to use instead of the zone's, e.g. a local S3 stand-in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+/// App created by proprion, with the credentials it was issued
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    /// Provider name the app was created on
+    pub provider: String,
+    /// Scaleway application ID or Exoscale role ID
+    pub id: String,
+    /// Description given at creation
+    #[serde(default)]
+    pub description: String,
+    /// Object key prefix the app is scoped to (e.g., apps/fitness-app/)
+    pub prefix: String,
+    /// API keys issued to the app, one per device
+    #[serde(default)]
+    pub keys: Vec<AppKey>,
+    /// What the app may do inside its prefix
+    #[serde(default)]
+    pub access: AccessMode,
+    /// Additional prefixes the app may access with the same mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_prefixes: Vec<String>,
+    /// Access to other apps' folders granted with `proprion grant`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub grants: Vec<Grant>,
+    /// Unix timestamp after which the app's keys are no longer valid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Lifecycle state of the app's credentials
+    #[serde(default)]
+    pub status: AppStatus,
+    /// For deleted apps, Unix timestamp after which `proprion purge` removes the record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purge_after: Option<u64>,
+    /// Maximum bytes the app may store, enforced by `proprion enforce-quotas`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<u64>,
+    /// Made read-only by `proprion enforce-quotas` for exceeding its quota
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub over_quota: bool,
+
+    // Single key stored inline by earlier versions, moved into `keys` on load
+    #[serde(default, rename = "access_key", skip_serializing)]
+    legacy_access_key: Option<String>,
+    #[serde(default, rename = "secret_key", skip_serializing)]
+    legacy_secret_key: Option<String>,
+    #[serde(default, rename = "retiring_key", skip_serializing)]
+    legacy_retiring_key: Option<RetiringKey>,
+}
+
+/// An API key issued to an app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppKey {
+    /// Name of the device or purpose (e.g., phone, laptop)
+    pub label: String,
+    /// S3 access key (Scaleway) or key ID (Exoscale)
+    pub access_key: String,
+    /// S3 secret key
+    pub secret_key: String,
+    /// Unix timestamp of when the key was issued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    /// Previous key left active by `rotate-key`, revoked by `rotate-key --finish`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retiring: Option<RetiringKey>,
+    /// Revoked by `revoke-all`; replaced by `reissue-keys`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub revoked: bool,
+}
+
+/// Lifecycle state of an app
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppStatus {
+    /// Credentials are valid
+    #[default]
+    Active,
+    /// Access paused by `proprion suspend-app`
+    Suspended,
+    /// Keys expired and were revoked by `proprion gc`
+    Expired,
+    /// Deleted with `delete-app`; restorable until purged
+    Deleted,
+}
+
+/// Access to another app's folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grant {
+    /// Name of the app whose folder is shared
+    pub from: String,
+    /// That app's prefix at the time of the grant
+    pub prefix: String,
+    /// What the grantee may do there
+    pub access: AccessMode,
+}
+
+/// An old app key kept valid during a rotation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetiringKey {
+    /// Access key (Scaleway) or key ID (Exoscale) to revoke
+    pub access_key: String,
+    /// Unix timestamp after which the key should be revoked
+    pub revoke_after: Option<u64>,
 }
 
 impl Config {
@@ -80,9 +206,13 @@ impl Config {
         let content = fs::read_to_string(&path)
             .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-        let config: Config = toml::from_str(&content)
+        let mut config: Config = toml::from_str(&content)
             .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
 
+        for app in config.apps.values_mut() {
+            app.migrate_legacy_key();
+        }
+
         Ok(config)
     }
 
@@ -99,7 +229,7 @@ impl Config {
         let content = toml::to_string_pretty(self)
             .context("Failed to serialize config")?;
 
-        fs::write(&path, content)
+        write_private(&path, content.as_bytes())
             .with_context(|| format!("Failed to write config file: {}", path.display()))?;
 
         Ok(())
@@ -124,19 +254,197 @@ impl Config {
     pub fn list_providers(&self) -> Vec<&String> {
         self.providers.keys().collect()
     }
+
+    /// Get an app by name
+    pub fn get_app(&self, name: &str) -> Option<&AppConfig> {
+        self.apps.get(name)
+    }
+
+    /// Add or update an app
+    pub fn set_app(&mut self, name: String, app: AppConfig) {
+        self.apps.insert(name, app);
+    }
+
+    /// Find the app with the given provider-side ID
+    pub fn find_app_by_id(&self, provider: &str, id: &str) -> Option<(&String, &AppConfig)> {
+        self.apps
+            .iter()
+            .find(|(_, app)| app.provider == provider && app.id == id)
+    }
+
+    /// Remove an app
+    pub fn remove_app(&mut self, name: &str) -> Option<AppConfig> {
+        self.apps.remove(name)
+    }
+}
+
+impl AppConfig {
+    /// A new app record without any keys.
+    pub fn new(
+        provider: &str,
+        id: String,
+        description: &str,
+        prefix: String,
+        access: AccessMode,
+        expires_at: Option<u64>,
+    ) -> Self {
+        Self {
+            provider: provider.to_string(),
+            id,
+            description: description.to_string(),
+            prefix,
+            keys: Vec::new(),
+            access,
+            extra_prefixes: Vec::new(),
+            grants: Vec::new(),
+            expires_at,
+            status: AppStatus::Active,
+            purge_after: None,
+            quota: None,
+            over_quota: false,
+            legacy_access_key: None,
+            legacy_secret_key: None,
+            legacy_retiring_key: None,
+        }
+    }
+
+    /// Move a key stored inline by earlier versions into `keys`.
+    fn migrate_legacy_key(&mut self) {
+        if let (Some(access_key), Some(secret_key)) =
+            (self.legacy_access_key.take(), self.legacy_secret_key.take())
+        {
+            self.keys.insert(
+                0,
+                AppKey {
+                    label: DEFAULT_KEY_LABEL.to_string(),
+                    access_key,
+                    secret_key,
+                    created_at: None,
+                    retiring: self.legacy_retiring_key.take(),
+                    revoked: false,
+                },
+            );
+        }
+    }
+
+    /// Get a key by label
+    pub fn get_key(&self, label: &str) -> Option<&AppKey> {
+        self.keys.iter().find(|k| k.label == label)
+    }
+
+    /// Get a key by label for modification
+    pub fn get_key_mut(&mut self, label: &str) -> Option<&mut AppKey> {
+        self.keys.iter_mut().find(|k| k.label == label)
+    }
+
+    /// Every prefix the app may access, with the access mode for each.
+    pub fn scopes(&self) -> Vec<(String, AccessMode)> {
+        let own = self.effective_access(self.access).into_iter().flat_map(|access| {
+            std::iter::once(&self.prefix)
+                .chain(&self.extra_prefixes)
+                .map(move |prefix| (prefix.clone(), access))
+        });
+        let grants = self
+            .grants
+            .iter()
+            .filter_map(|g| Some((g.prefix.clone(), self.effective_access(g.access)?)));
+        own.chain(grants).collect()
+    }
+
+    /// The mode actually granted for `access`: read-only while the app is over
+    /// its quota, or nothing if `access` doesn't include reading.
+    pub fn effective_access(&self, access: AccessMode) -> Option<AccessMode> {
+        if self.over_quota {
+            access.read_only()
+        } else {
+            Some(access)
+        }
+    }
+
+    /// Seconds until the app's key expires, or None if it never does.
+    /// Zero means the key has already expired.
+    pub fn expires_in(&self, now: u64) -> Option<u64> {
+        self.expires_at.map(|at| at.saturating_sub(now))
+    }
+}
+
+impl std::fmt::Display for AppStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AppStatus::Active => "active",
+            AppStatus::Suspended => "suspended",
+            AppStatus::Expired => "expired",
+            AppStatus::Deleted => "deleted",
+        };
+        f.write_str(name)
+    }
+}
+
+impl ProviderConfig {
+    /// Get the S3 endpoint URL
+    pub fn endpoint(&self) -> String {
+        match self {
+            ProviderConfig::Scaleway(cfg) => cfg.endpoint(),
+            ProviderConfig::Exoscale(cfg) => cfg.endpoint(),
+        }
+    }
+
+    /// Get the S3 region (Scaleway region or Exoscale zone)
+    pub fn region(&self) -> &str {
+        match self {
+            ProviderConfig::Scaleway(cfg) => &cfg.region,
+            ProviderConfig::Exoscale(cfg) => &cfg.zone,
+        }
+    }
+
+    /// Regions or zones of this provider type where proprion can be used
+    pub fn known_regions(&self) -> &'static [&'static str] {
+        match self {
+            ProviderConfig::Scaleway(_) => SCALEWAY_REGIONS,
+            ProviderConfig::Exoscale(_) => EXOSCALE_ZONES,
+        }
+    }
+
+    /// Get the bucket name
+    pub fn bucket(&self) -> &str {
+        match self {
+            ProviderConfig::Scaleway(cfg) => &cfg.bucket,
+            ProviderConfig::Exoscale(cfg) => &cfg.bucket,
+        }
+    }
+
+    /// Point the provider at another bucket
+    pub fn set_bucket(&mut self, bucket: String) {
+        match self {
+            ProviderConfig::Scaleway(cfg) => cfg.bucket = bucket,
+            ProviderConfig::Exoscale(cfg) => cfg.bucket = bucket,
+        }
+    }
+
+    /// Get the provider's root S3 credentials as (access key, secret key)
+    pub fn credentials(&self) -> (&str, &str) {
+        match self {
+            ProviderConfig::Scaleway(cfg) => (&cfg.access_key, &cfg.secret_key),
+            ProviderConfig::Exoscale(cfg) => (&cfg.api_key, &cfg.api_secret),
+        }
+    }
 }
 
 impl ScalewayProviderConfig {
     /// Get the S3 endpoint URL
     pub fn endpoint(&self) -> String {
-        format!("https://s3.{}.scw.cloud", self.region)
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{}.scw.cloud", self.region))
     }
 }
 
 impl ExoscaleProviderConfig {
     /// Get the S3 endpoint URL
     pub fn endpoint(&self) -> String {
-        format!("https://sos-{}.exo.io", self.zone)
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://sos-{}.exo.io", self.zone))
     }
 
     /// Get the API base URL for the zone
@@ -144,3 +452,36 @@ impl ExoscaleProviderConfig {
         format!("https://api-{}.exoscale.com/v2", self.zone)
     }
 }
+
+/// Write a file only its owner can read, for files holding secrets.
+///
+/// The content goes to a private temp file that is then renamed over `path`, so
+/// the secrets are never readable by others and a failed write can't leave a
+/// truncated file behind.
+pub fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let mut temp_name = path.file_name().context("Path has no file name")?.to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&temp_path)
+        .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+    // A temp file left over from an interrupted write keeps its old mode
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to set permissions on {}", temp_path.display()))?;
+    }
+    file.write_all(content)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
@@ -4,6 +4,7 @@ use hmac::{Hmac, Mac};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
@@ -38,6 +39,10 @@ pub struct IamRole {
     pub id: String,
     pub name: Option<String>,
     pub description: Option<String>,
+    #[serde(default)]
+    pub policy: Option<RolePolicy>,
+    /// Roles created by early versions of proprion are not editable
+    pub editable: Option<bool>,
 }
 
 /// Response from async operations like create-iam-role
@@ -92,29 +97,25 @@ struct CreateRoleRequest {
     policy: RolePolicy,
 }
 
-#[derive(Serialize)]
+/// Policy of an IAM role, per service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
-struct RolePolicy {
-    default_service_strategy: String,
-    services: RolePolicyServices,
-}
-
-#[derive(Serialize)]
-struct RolePolicyServices {
-    sos: ServicePolicy,
+pub struct RolePolicy {
+    pub default_service_strategy: String,
+    pub services: BTreeMap<String, ServicePolicy>,
 }
 
-#[derive(Serialize)]
-struct ServicePolicy {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServicePolicy {
     #[serde(rename = "type")]
-    policy_type: String,
-    rules: Option<Vec<PolicyRule>>,
+    pub policy_type: String,
+    pub rules: Option<Vec<PolicyRule>>,
 }
 
-#[derive(Serialize)]
-struct PolicyRule {
-    action: String,
-    expression: String,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub action: String,
+    pub expression: String,
 }
 
 #[derive(Serialize)]
@@ -124,6 +125,64 @@ struct CreateApiKeyRequest {
     role_id: String,
 }
 
+/// SOS operations allowed on objects under a key prefix
+pub struct PrefixScope {
+    pub prefix: String,
+    pub operations: Vec<&'static str>,
+}
+
+/// Build a deny-by-default role policy granting each scope's operations.
+///
+/// Resources: resources.bucket for bucket, parameters.key for object key.
+/// `list-objects` is a bucket operation, scoped by the listing's parameters.prefix.
+pub fn sos_policy(bucket: &str, scopes: &[PrefixScope]) -> RolePolicy {
+    let mut rules = Vec::new();
+
+    // Listing only under each scope's prefix, so other folders' object names stay hidden
+    for scope in scopes.iter().filter(|s| s.operations.contains(&"list-objects")) {
+        rules.push(PolicyRule {
+            action: "allow".to_string(),
+            expression: format!(
+                "operation == 'list-objects' && resources.bucket == '{}' && parameters.prefix.startsWith('{}')",
+                bucket, scope.prefix
+            ),
+        });
+    }
+
+    // Object operations only on each scope's prefix
+    for scope in scopes {
+        let object_ops: Vec<String> = scope
+            .operations
+            .iter()
+            .filter(|op| **op != "list-objects")
+            .map(|op| format!("'{}'", op))
+            .collect();
+        if object_ops.is_empty() {
+            continue;
+        }
+        rules.push(PolicyRule {
+            action: "allow".to_string(),
+            expression: format!(
+                "operation in [{}] && resources.bucket == '{}' && parameters.key.startsWith('{}')",
+                object_ops.join(", "),
+                bucket,
+                scope.prefix
+            ),
+        });
+    }
+
+    RolePolicy {
+        default_service_strategy: "deny".to_string(),
+        services: BTreeMap::from([(
+            "sos".to_string(),
+            ServicePolicy {
+                policy_type: "rules".to_string(),
+                rules: Some(rules),
+            },
+        )]),
+    }
+}
+
 impl Client {
     /// Create a new Exoscale API client.
     pub fn new(api_key: String, api_secret: String, zone: &str) -> Self {
@@ -196,50 +255,45 @@ impl Client {
         }
     }
 
-    /// Create an IAM role with SOS access scoped to a bucket prefix.
+    /// The API server's clock, read from the Date header of an unsigned request.
+    ///
+    /// Signatures expire 10 minutes after the local time, so a skewed local
+    /// clock makes every signed request fail.
+    pub async fn server_time(&self) -> Result<SystemTime> {
+        let url = format!("{}/v2/zone", self.api_base);
+        let response = self.http.get(&url).send().await?;
+        let status = response.status().as_u16();
+
+        response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| httpdate::parse_http_date(date).ok())
+            .ok_or_else(|| ExoscaleError::Api {
+                status,
+                message: "Response has no valid Date header".to_string(),
+            })
+    }
+
+    /// Create an IAM role with SOS access scoped to bucket prefixes.
+    ///
+    /// The role is editable so its policy can later be changed with
+    /// [`Client::update_role_policy`].
     pub async fn create_role(
         &self,
         name: &str,
         description: &str,
         bucket: &str,
-        prefix: &str,
+        scopes: &[PrefixScope],
     ) -> Result<IamRole> {
         let path = "/v2/iam-role";
         let url = format!("{}{}", self.api_base, path);
 
-        // Create policy that only allows SOS operations on specific bucket/prefix
-        // Operations: get-object, put-object, delete-object, head-object, list-objects
-        // Resources: resources.bucket for bucket, parameters.key for object key
         let payload = CreateRoleRequest {
             name: name.to_string(),
             description: description.to_string(),
-            editable: false,
-            policy: RolePolicy {
-                default_service_strategy: "deny".to_string(),
-                services: RolePolicyServices {
-                    sos: ServicePolicy {
-                        policy_type: "rules".to_string(),
-                        rules: Some(vec![
-                            // Allow listing objects in the bucket (needed for navigation)
-                            PolicyRule {
-                                action: "allow".to_string(),
-                                expression: format!(
-                                    "operation == 'list-objects' && resources.bucket == '{}'",
-                                    bucket
-                                ),
-                            },
-                            // Allow object operations only on the app's prefix
-                            PolicyRule {
-                                action: "allow".to_string(),
-                                expression: format!(
-                                    "operation in ['get-object', 'put-object', 'delete-object', 'head-object'] && resources.bucket == '{}' && parameters.key.startsWith('{}')",
-                                    bucket, prefix
-                                ),
-                            },
-                        ]),
-                    },
-                },
-            },
+            editable: true,
+            policy: sos_policy(bucket, scopes),
         };
 
         let body = serde_json::to_string(&payload)
@@ -273,6 +327,8 @@ impl Client {
             id: role_id,
             name: Some(name.to_string()),
             description: Some(description.to_string()),
+            policy: Some(payload.policy),
+            editable: Some(true),
         })
     }
 
@@ -290,6 +346,33 @@ impl Client {
         Ok(roles.iam_roles)
     }
 
+    /// Replace the policy of an existing (editable) IAM role.
+    pub async fn update_role_policy(
+        &self,
+        role_id: &str,
+        bucket: &str,
+        scopes: &[PrefixScope],
+    ) -> Result<()> {
+        let path = format!("/v2/iam-role/{}:policy", role_id);
+        let url = format!("{}{}", self.api_base, path);
+
+        let body = serde_json::to_string(&sos_policy(bucket, scopes))
+            .map_err(|e| ExoscaleError::Signature(e.to_string()))?;
+
+        let auth = self.sign_request("PUT", &path, &body)?;
+
+        let response = self
+            .http
+            .put(&url)
+            .headers(self.headers(&auth))
+            .body(body)
+            .send()
+            .await?;
+
+        self.check_response(response).await?;
+        Ok(())
+    }
+
     /// Delete an IAM role.
     pub async fn delete_role(&self, role_id: &str) -> Result<()> {
         let path = format!("/v2/iam-role/{}", role_id);
@@ -368,3 +451,51 @@ impl Client {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expressions(policy: &RolePolicy) -> Vec<&str> {
+        policy.services["sos"]
+            .rules
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|rule| rule.expression.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sos_policy_scopes_listing_and_objects_to_prefixes() {
+        let scopes = [
+            PrefixScope {
+                prefix: "apps/a/".to_string(),
+                operations: vec!["list-objects", "get-object", "put-object"],
+            },
+            PrefixScope {
+                prefix: "apps/b/".to_string(),
+                operations: vec!["put-object"],
+            },
+        ];
+        let policy = sos_policy("bucket", &scopes);
+
+        assert_eq!(policy.default_service_strategy, "deny");
+        assert_eq!(policy.services["sos"].policy_type, "rules");
+        assert_eq!(
+            expressions(&policy),
+            [
+                "operation == 'list-objects' && resources.bucket == 'bucket' && parameters.prefix.startsWith('apps/a/')",
+                "operation in ['get-object', 'put-object'] && resources.bucket == 'bucket' && parameters.key.startsWith('apps/a/')",
+                "operation in ['put-object'] && resources.bucket == 'bucket' && parameters.key.startsWith('apps/b/')",
+            ]
+        );
+    }
+
+    #[test]
+    fn sos_policy_without_scopes_denies_everything() {
+        let policy = sos_policy("bucket", &[]);
+        assert_eq!(policy.default_service_strategy, "deny");
+        assert!(expressions(&policy).is_empty());
+    }
+}
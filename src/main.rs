@@ -4,11 +4,18 @@ use s3::creds::Credentials;
 use s3::region::Region;
 use s3::{Bucket, BucketConfiguration};
 
+mod access;
+mod archive;
 mod config;
 mod exoscale;
+mod pricing;
 mod scaleway;
 
-use config::{Config, ProviderConfig, ScalewayProviderConfig, ExoscaleProviderConfig};
+use access::AccessMode;
+use config::{
+    AppConfig, AppKey, AppStatus, Config, Grant, ProviderConfig, RetiringKey, ScalewayProviderConfig,
+    ExoscaleProviderConfig, DEFAULT_KEY_LABEL, EXOSCALE_ZONES, SCALEWAY_REGIONS,
+};
 
 #[derive(Parser)]
 #[command(name = "proprion")]
@@ -23,70 +30,95 @@ struct Cli {
     command: Commands,
 }
 
+/// Flags left out of `add-provider` are asked for interactively
 #[derive(Subcommand)]
 enum AddProviderCommand {
     /// Add Scaleway provider
     Scaleway {
         /// Provider name (your choice, e.g., "my-scaleway")
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
         /// Access key
         #[arg(long)]
-        access_key: String,
+        access_key: Option<String>,
 
         /// Secret key
         #[arg(long)]
-        secret_key: String,
+        secret_key: Option<String>,
 
         /// Region (e.g., fr-par, nl-ams, pl-waw)
         #[arg(long)]
-        region: String,
+        region: Option<String>,
 
         /// Bucket name
         #[arg(long)]
-        bucket: String,
+        bucket: Option<String>,
 
         /// Organization ID
         #[arg(long)]
-        organization_id: String,
+        organization_id: Option<String>,
 
         /// Project ID
         #[arg(long)]
-        project_id: String,
+        project_id: Option<String>,
+
+        /// S3 endpoint, if not the region's (e.g., a local S3 stand-in for testing)
+        #[arg(long)]
+        endpoint: Option<String>,
+
+        /// Create the bucket if it doesn't exist yet
+        #[arg(long)]
+        create_bucket: bool,
+
+        /// Save without checking the region, credentials and bucket
+        #[arg(long)]
+        skip_validation: bool,
     },
 
     /// Add Exoscale provider
     Exoscale {
         /// Provider name (your choice, e.g., "my-exoscale")
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
         /// API key
         #[arg(long)]
-        api_key: String,
+        api_key: Option<String>,
 
         /// API secret
         #[arg(long)]
-        api_secret: String,
+        api_secret: Option<String>,
 
         /// Zone (e.g., ch-gva-2, de-fra-1, ch-dk-2)
         #[arg(long)]
-        zone: String,
+        zone: Option<String>,
 
         /// Bucket name
         #[arg(long)]
-        bucket: String,
+        bucket: Option<String>,
+
+        /// S3 endpoint, if not the zone's (e.g., a local S3 stand-in for testing)
+        #[arg(long)]
+        endpoint: Option<String>,
+
+        /// Create the bucket if it doesn't exist yet
+        #[arg(long)]
+        create_bucket: bool,
+
+        /// Save without checking the region, credentials and bucket
+        #[arg(long)]
+        skip_validation: bool,
     },
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Add a new provider configuration
+    /// Add a new provider configuration (asks for anything not given as a flag)
     #[command(name = "add-provider")]
     AddProvider {
         #[command(subcommand)]
-        provider: AddProviderCommand,
+        provider: Option<AddProviderCommand>,
     },
 
     /// List configured providers
@@ -105,20 +137,40 @@ enum Commands {
     #[command(name = "config-path")]
     ConfigPath,
 
+    /// Check that providers are configured correctly, and explain how to fix problems
+    #[command(name = "doctor")]
+    Doctor {
+        /// Only check this provider (default: all providers)
+        #[arg(short, long)]
+        provider: Option<String>,
+    },
+
     /// Create credentials for a new app
     #[command(name = "create-app")]
     CreateApp {
-        /// Provider name (from config)
+        /// Provider name (from config); asked for if left out
         #[arg(short, long)]
-        provider: String,
+        provider: Option<String>,
 
-        /// App name
+        /// App name; asked for if left out
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
-        /// App description
+        /// App description; asked for if left out
         #[arg(short, long)]
-        description: String,
+        description: Option<String>,
+
+        /// Revoke the app's key after this long (e.g., 12h, 30d)
+        #[arg(long)]
+        expires_in: Option<String>,
+
+        /// What the app may do inside its folder
+        #[arg(long, value_enum, default_value_t = AccessMode::Full)]
+        access: AccessMode,
+
+        /// Maximum storage for the app (e.g., 500MB, 10GiB), enforced by `enforce-quotas`
+        #[arg(long)]
+        quota: Option<String>,
     },
 
     /// List existing apps
@@ -127,18 +179,396 @@ enum Commands {
         /// Provider name (from config)
         #[arg(short, long)]
         provider: String,
+
+        /// Also list each app's keys
+        #[arg(long)]
+        keys: bool,
+
+        /// Also list IAM roles or applications proprion didn't create
+        #[arg(long)]
+        all: bool,
     },
 
     /// Delete an app and its credentials
+    ///
+    /// Keys are revoked right away, but the app's data and record are kept so it
+    /// can be brought back with `restore-app` until the recovery window ends.
     #[command(name = "delete-app")]
     DeleteApp {
+        /// App name
+        #[arg(short, long, conflicts_with_all = ["provider", "app_id"], required_unless_present = "app_id")]
+        name: Option<String>,
+
+        /// Provider name (from config), when deleting by ID
+        #[arg(short, long, requires = "app_id")]
+        provider: Option<String>,
+
+        /// Application ID (Scaleway) or role ID (Exoscale) to delete
+        #[arg(short, long, requires = "provider")]
+        app_id: Option<String>,
+
+        /// How long the app can still be restored (e.g., 7d, 30d)
+        #[arg(long, default_value = "7d")]
+        keep_for: String,
+
+        /// Also permanently erase every object version in the app's folder
+        #[arg(long, requires = "name")]
+        wipe_data: bool,
+
+        /// Don't ask for confirmation before erasing data
+        #[arg(short, long, requires = "wipe_data")]
+        yes: bool,
+    },
+
+    /// Bring back an app deleted with `delete-app`, with fresh keys
+    #[command(name = "restore-app")]
+    RestoreApp {
+        /// App name
+        name: String,
+    },
+
+    /// Permanently remove deleted apps whose recovery window has ended
+    #[command(name = "purge")]
+    Purge {
+        /// Purge this deleted app now, even if its recovery window is still open
+        #[arg(short, long)]
+        app: Option<String>,
+    },
+
+    /// Download all objects in an app's folder into a compressed archive
+    #[command(name = "export-app")]
+    ExportApp {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// Archive file to write (e.g., fitness.tar.zst)
+        #[arg(long)]
+        to: std::path::PathBuf,
+    },
+
+    /// Upload the objects of an archive made by `export-app` into an app's folder
+    #[command(name = "import-app")]
+    ImportApp {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// Archive file to read
+        #[arg(long)]
+        from: std::path::PathBuf,
+    },
+
+    /// Move an app to another provider, copying its data
+    ///
+    /// Progress is saved after every step and every object, so an interrupted migration
+    /// can be resumed by running the same command again.
+    #[command(name = "migrate-app")]
+    MigrateApp {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// Provider the app is on now
+        #[arg(long)]
+        from: String,
+
+        /// Provider to move the app to
+        #[arg(long)]
+        to: String,
+
+        /// Once the copy is verified, delete the app and its data on the source provider
+        #[arg(long)]
+        delete_source: bool,
+
+        /// Don't ask for confirmation before deleting the source
+        #[arg(short, long, requires = "delete_source")]
+        yes: bool,
+    },
+
+    /// Copy all app data from one provider's bucket to another's
+    ///
+    /// Only new or changed objects are copied, using a state file kept next to the
+    /// config file. Nothing is deleted on the target unless --mirror-deletes is set.
+    #[command(name = "replicate")]
+    Replicate {
+        /// Provider to copy from
+        #[arg(long)]
+        source: String,
+
+        /// Provider to copy to
+        #[arg(long)]
+        target: String,
+
+        /// Delete objects from the target that were replicated earlier but are gone from the source
+        #[arg(long)]
+        mirror_deletes: bool,
+
+        /// Keep running, replicating again after this long (e.g., 10m, 1h)
+        #[arg(long)]
+        watch: Option<String>,
+    },
+
+    /// Show how much storage each app uses, and how that changed since the last run
+    #[command(name = "usage")]
+    Usage {
+        /// Only this provider (default: all providers)
+        #[arg(short, long)]
+        provider: Option<String>,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Estimate the monthly cost of each app from its current storage usage
+    #[command(name = "cost")]
+    Cost {
+        /// Only this provider (default: all providers)
+        #[arg(short, long)]
+        provider: Option<String>,
+
+        /// Also price each provider's usage at every other configured provider
+        #[arg(long)]
+        compare: bool,
+
+        /// Expected monthly downloads per provider (e.g., 50GB), priced as egress
+        #[arg(long)]
+        egress: Option<String>,
+    },
+
+    /// Check with an app's own key that it can use its folder and nothing else
+    ///
+    /// Probes writing, reading, listing and deleting inside the app's folder, in a
+    /// sibling folder and at the bucket root, and prints a pass/fail matrix.
+    #[command(name = "verify-app")]
+    VerifyApp {
+        /// App name
+        #[arg(short, long, required_unless_present = "all")]
+        app: Option<String>,
+
+        /// Verify every active or suspended app
+        #[arg(long, conflicts_with_all = ["app", "key"])]
+        all: bool,
+
+        /// Label of the key to use (default: the app's first key)
+        #[arg(short, long)]
+        key: Option<String>,
+    },
+
+    /// Run a command with an app's S3 credentials in its environment
+    ///
+    /// Sets AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_ENDPOINT_URL, AWS_REGION,
+    /// PROPRION_BUCKET and PROPRION_PREFIX, then runs the command. Its exit code
+    /// (or terminating signal) is passed through.
+    #[command(name = "exec")]
+    Exec {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// Label of the key to use (default: the app's first key)
+        #[arg(short, long)]
+        key: Option<String>,
+
+        /// Command to run, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Issue a new API key for an app and retire the old one
+    #[command(name = "rotate-key")]
+    RotateKey {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// Label of the key to rotate
+        #[arg(short, long, default_value = DEFAULT_KEY_LABEL)]
+        key: String,
+
+        /// Keep the old key valid for this long instead of asking (e.g., 30m, 24h, 7d)
+        #[arg(long, conflicts_with = "finish")]
+        grace: Option<String>,
+
+        /// Revoke the old key left over from a previous rotation
+        #[arg(long)]
+        finish: bool,
+    },
+
+    /// Issue an additional key for an app, e.g., one per device
+    #[command(name = "add-key")]
+    AddKey {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// Label for the key (e.g., phone, laptop)
+        #[arg(short, long)]
+        label: String,
+    },
+
+    /// Revoke a single key of an app, leaving its other keys working
+    #[command(name = "revoke-key")]
+    RevokeKey {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// Label of the key to revoke
+        #[arg(short, long)]
+        label: String,
+    },
+
+    /// Change what an existing app may access
+    #[command(name = "update-app")]
+    UpdateApp {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+
+        /// New access mode
+        #[arg(long, value_enum)]
+        access: Option<AccessMode>,
+
+        /// Also allow access to this prefix (repeatable, e.g., shared/photos/)
+        #[arg(long)]
+        add_prefix: Vec<String>,
+
+        /// Remove a prefix added with --add-prefix (repeatable)
+        #[arg(long)]
+        remove_prefix: Vec<String>,
+
+        /// New storage quota (e.g., 10GiB), or "none" to remove it
+        #[arg(long)]
+        quota: Option<String>,
+    },
+
+    /// Let one app access another app's folder
+    #[command(name = "grant")]
+    Grant {
+        /// App whose folder is shared
+        #[arg(long)]
+        from: String,
+
+        /// App that gets access
+        #[arg(long)]
+        to: String,
+
+        /// What the receiving app may do in the shared folder
+        #[arg(long, value_enum, default_value_t = AccessMode::Read)]
+        access: AccessMode,
+    },
+
+    /// Remove access granted with `grant`
+    #[command(name = "revoke-grant")]
+    RevokeGrant {
+        /// App whose folder was shared
+        #[arg(long)]
+        from: String,
+
+        /// App that had access
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Pause all access of an app without deleting its keys or data
+    #[command(name = "suspend-app")]
+    SuspendApp {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+    },
+
+    /// Restore access of a suspended app
+    #[command(name = "resume-app")]
+    ResumeApp {
+        /// App name
+        #[arg(short, long)]
+        app: String,
+    },
+
+    /// Emergency: revoke every proprion-managed API key
+    #[command(name = "revoke-all")]
+    RevokeAll {
+        /// Only revoke keys on this provider (default: all providers)
+        #[arg(short, long)]
+        provider: Option<String>,
+
+        /// Where to write the JSON report (default: next to the config file)
+        #[arg(long)]
+        report: Option<std::path::PathBuf>,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Issue fresh keys for keys revoked by `revoke-all`
+    ///
+    /// Exoscale roles created by early versions of proprion, which can't be edited,
+    /// are recreated as editable along the way, with new keys for the app.
+    #[command(name = "reissue-keys")]
+    ReissueKeys {
+        /// Only reissue keys on this provider
+        #[arg(short, long)]
+        provider: Option<String>,
+
+        /// Only reissue keys of this app
+        #[arg(short, long)]
+        app: Option<String>,
+    },
+
+    /// Record an IAM role or application made by hand as a proprion app
+    ///
+    /// The app's folder and access mode are inferred from the role policy (Exoscale)
+    /// or the bucket-policy statements of the application (Scaleway).
+    #[command(name = "adopt")]
+    Adopt {
         /// Provider name (from config)
         #[arg(short, long)]
         provider: String,
 
-        /// Application ID to delete
+        /// Exoscale IAM role ID
+        #[arg(long, conflicts_with = "application_id", required_unless_present = "application_id")]
+        role_id: Option<String>,
+
+        /// Scaleway IAM application ID
+        #[arg(long)]
+        application_id: Option<String>,
+
+        /// Name to record the app under
+        #[arg(short, long)]
+        name: String,
+
+        /// Replace the existing permissions with exactly what proprion grants
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// Compare app records with the IAM objects and bucket policy in the cloud accounts
+    #[command(name = "audit")]
+    Audit {
+        /// Only audit this provider (default: all providers)
+        #[arg(short, long)]
+        provider: Option<String>,
+    },
+
+    /// Make apps over their storage quota read-only, and restore apps back under it
+    #[command(name = "enforce-quotas")]
+    EnforceQuotas,
+
+    /// Revoke expired app keys and old keys whose rotation grace period has ended,
+    /// and delete IAM objects and bucket-policy statements left behind by failed runs
+    #[command(name = "gc")]
+    Gc {
+        /// Print what would be revoked and deleted, and change nothing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Delete leftovers without asking for confirmation
         #[arg(short, long)]
-        app_id: String,
+        yes: bool,
     },
 }
 
@@ -148,15 +578,24 @@ async fn main() -> Result<()> {
 
     match cli.command {
         Commands::AddProvider { provider } => {
-            let (name, provider_config) = match provider {
+            // The wizard checks each answer as it goes
+            let (provider, validated) = match provider {
+                Some(provider) if provider.is_complete() => (provider, false),
+                provider => (provider_wizard(provider).await?, true),
+            };
+
+            let (name, provider_config, create_bucket, skip_validation) = match provider {
                 AddProviderCommand::Scaleway {
-                    name,
-                    access_key,
-                    secret_key,
-                    region,
-                    bucket,
-                    organization_id,
-                    project_id,
+                    name: Some(name),
+                    access_key: Some(access_key),
+                    secret_key: Some(secret_key),
+                    region: Some(region),
+                    bucket: Some(bucket),
+                    organization_id: Some(organization_id),
+                    project_id: Some(project_id),
+                    endpoint,
+                    create_bucket,
+                    skip_validation,
                 } => {
                     let config = ProviderConfig::Scaleway(ScalewayProviderConfig {
                         access_key,
@@ -165,26 +604,38 @@ async fn main() -> Result<()> {
                         project_id,
                         region,
                         bucket,
+                        endpoint,
                     });
-                    (name, config)
+                    (name, config, create_bucket, skip_validation)
                 }
                 AddProviderCommand::Exoscale {
-                    name,
-                    api_key,
-                    api_secret,
-                    zone,
-                    bucket,
+                    name: Some(name),
+                    api_key: Some(api_key),
+                    api_secret: Some(api_secret),
+                    zone: Some(zone),
+                    bucket: Some(bucket),
+                    endpoint,
+                    create_bucket,
+                    skip_validation,
                 } => {
                     let config = ProviderConfig::Exoscale(ExoscaleProviderConfig {
                         api_key,
                         api_secret,
                         zone,
                         bucket,
+                        endpoint,
                     });
-                    (name, config)
+                    (name, config, create_bucket, skip_validation)
                 }
+                _ => unreachable!("the wizard fills in every flag"),
             };
 
+            if skip_validation {
+                println!("Skipping validation.");
+            } else if !validated {
+                validate_provider(&name, &provider_config, create_bucket).await?;
+            }
+
             let mut config = Config::load(cli.config.as_ref())?;
             config.set_provider(name.clone(), provider_config);
             config.save(cli.config.as_ref())?;
@@ -222,6 +673,37 @@ async fn main() -> Result<()> {
             }
         }
 
+        Commands::Doctor { provider } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let mut names: Vec<&String> = match &provider {
+                Some(name) => {
+                    let (name, _) = config
+                        .providers
+                        .get_key_value(name)
+                        .with_context(|| format!("Provider '{}' not found.", name))?;
+                    vec![name]
+                }
+                None => config.list_providers(),
+            };
+            names.sort();
+            if names.is_empty() {
+                println!("No providers configured.");
+                println!("Add one with: proprion add-provider --help");
+                return Ok(());
+            }
+
+            let mut problems = 0;
+            for name in names {
+                problems += diagnose_provider(name, &config.providers[name]).await;
+                println!();
+            }
+
+            if problems > 0 {
+                anyhow::bail!("Found {} problem(s).", problems);
+            }
+            println!("No problems found.");
+        }
+
         Commands::ConfigPath => {
             println!("{}", Config::path(cli.config.as_ref())?.display());
         }
@@ -230,23 +712,63 @@ async fn main() -> Result<()> {
             provider,
             name,
             description,
+            expires_in,
+            access,
+            quota,
         } => {
-            let config = Config::load(cli.config.as_ref())?;
+            let mut config = Config::load(cli.config.as_ref())?;
+            let provider = match provider {
+                Some(provider) => provider,
+                None => {
+                    let mut names: Vec<&String> = config.list_providers();
+                    names.sort();
+                    if names.is_empty() {
+                        anyhow::bail!("No providers configured. Add one with: proprion add-provider");
+                    }
+                    names[prompt_choice("Provider", &names)?].clone()
+                }
+            };
             let provider_config = config
                 .get_provider(&provider)
                 .with_context(|| format!("Provider '{}' not found. Run 'proprion list-providers' to see configured providers.", provider))?;
 
-            match provider_config {
+            let name = or_prompt(name, "App name (e.g., fitness-app)")?;
+            validate_name("app name", &name)?;
+            match config.get_app(&name) {
+                Some(app) if app.status == AppStatus::Deleted => anyhow::bail!(
+                    "App '{}' was deleted but can still be restored. Run 'proprion restore-app {}' or 'proprion purge --app {}'.",
+                    name,
+                    name,
+                    name
+                ),
+                Some(_) => anyhow::bail!("App '{}' already exists.", name),
+                None => {}
+            }
+
+            let description = or_prompt(description, "Description")?;
+
+            let expires_at = expires_in
+                .as_deref()
+                .map(parse_duration)
+                .transpose()?
+                .map(|secs| now_unix() + secs);
+            let quota = quota.as_deref().map(parse_size).transpose()?;
+
+            let mut app = match provider_config {
                 ProviderConfig::Scaleway(cfg) => {
-                    create_scaleway_app(cfg, &name, &description).await?;
+                    create_scaleway_app(cfg, &provider, &name, &description, expires_at, access).await?
                 }
                 ProviderConfig::Exoscale(cfg) => {
-                    create_exoscale_app(cfg, &name, &description).await?;
+                    create_exoscale_app(cfg, &provider, &name, &description, expires_at, access).await?
                 }
-            }
+            };
+            app.quota = quota;
+
+            config.set_app(name, app);
+            config.save(cli.config.as_ref())?;
         }
 
-        Commands::ListApps { provider } => {
+        Commands::ListApps { provider, keys, all } => {
             let config = Config::load(cli.config.as_ref())?;
             let provider_config = config
                 .get_provider(&provider)
@@ -254,316 +776,5178 @@ async fn main() -> Result<()> {
 
             match provider_config {
                 ProviderConfig::Scaleway(cfg) => {
-                    list_scaleway_apps(cfg).await?;
+                    list_scaleway_apps(cfg, &config, &provider, keys, all).await?;
                 }
                 ProviderConfig::Exoscale(cfg) => {
-                    list_exoscale_apps(cfg).await?;
+                    list_exoscale_apps(cfg, &config, &provider, keys, all).await?;
                 }
             }
         }
 
-        Commands::DeleteApp { provider, app_id } => {
-            let config = Config::load(cli.config.as_ref())?;
-            let provider_config = config
-                .get_provider(&provider)
-                .with_context(|| format!("Provider '{}' not found.", provider))?;
+        Commands::DeleteApp {
+            name,
+            provider,
+            app_id,
+            keep_for,
+            wipe_data,
+            yes,
+        } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let keep_for = parse_duration(&keep_for)?;
 
-            match provider_config {
-                ProviderConfig::Scaleway(cfg) => {
-                    delete_scaleway_app(cfg, &app_id).await?;
+            // Resolve the app record, either by name or by provider-side ID
+            let name = match (name, provider.as_deref(), app_id.as_deref()) {
+                (Some(name), _, _) => {
+                    config
+                        .get_app(&name)
+                        .with_context(|| format!("App '{}' not found.", name))?;
+                    Some(name)
                 }
-                ProviderConfig::Exoscale(cfg) => {
-                    delete_exoscale_app(cfg, &app_id).await?;
+                (None, Some(provider), Some(app_id)) => {
+                    config.find_app_by_id(provider, app_id).map(|(name, _)| name.clone())
                 }
-            }
-        }
-    }
+                _ => unreachable!("clap enforces --name or --provider with --app-id"),
+            };
 
-    Ok(())
-}
+            let Some(name) = name else {
+                // Not created by this proprion install - nothing to keep, delete outright
+                let provider = provider.expect("checked above");
+                let app_id = app_id.expect("checked above");
+                let provider_config = config
+                    .get_provider(&provider)
+                    .with_context(|| format!("Provider '{}' not found.", provider))?;
+                match provider_config {
+                    ProviderConfig::Scaleway(cfg) => {
+                        delete_scaleway_app(cfg, &app_id, None).await?;
+                    }
+                    ProviderConfig::Exoscale(cfg) => {
+                        delete_exoscale_app(cfg, &app_id).await?;
+                    }
+                }
+                return Ok(());
+            };
 
-// ============================================================================
-// Scaleway Implementation
-// ============================================================================
+            let mut app_config = config.apps[&name].clone();
+            // A deleted app can still have its data wiped
+            if app_config.status == AppStatus::Deleted && !wipe_data {
+                anyhow::bail!("App '{}' is already deleted.", name);
+            }
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
 
-async fn create_scaleway_app(cfg: &ScalewayProviderConfig, name: &str, description: &str) -> Result<()> {
-    let client = scaleway::Client::new(cfg.secret_key.clone());
-    let app_prefix = format!("apps/{}", name);
+            let versions = if wipe_data {
+                let versions = list_prefix_versions(provider_config, &app_config.prefix)?;
+                print_prefix_versions(provider_config, &app_config.prefix, &versions);
+                if !yes && !confirm("Permanently erase all of this data? This cannot be undone.")? {
+                    println!("Aborted.");
+                    return Ok(());
+                }
+                Some(versions)
+            } else {
+                None
+            };
 
-    println!("Creating app '{}' on Scaleway...", name);
+            if app_config.status != AppStatus::Deleted {
+                soft_delete_app(provider_config, &name, &mut app_config, keep_for).await?;
+            }
 
-    // Step 1: Create bucket if needed
-    println!("  [1/5] Checking/creating bucket '{}'...", cfg.bucket);
-    ensure_bucket_exists(&cfg.access_key, &cfg.secret_key, &cfg.region, &cfg.bucket, "scaleway").await?;
-    println!("        Bucket ready");
+            // Save before wiping, so the revoked keys are recorded even if erasure fails
+            config.set_app(name.clone(), app_config);
+            let revoked = revoke_grants_from(&mut config, &name).await;
+            config.save(cli.config.as_ref())?;
+            revoked?;
 
-    // Step 2: Create application
-    println!("  [2/5] Creating IAM application...");
-    let app = client
-        .create_application(name, description, &cfg.organization_id)
-        .await
-        .context("Failed to create application")?;
-    println!("        Application ID: {}", app.id);
+            if let Some(versions) = versions {
+                let app_config = &config.apps[&name];
+                let provider_config = config
+                    .get_provider(&app_config.provider)
+                    .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+                wipe_app_data(provider_config, &name, app_config, &versions)?;
+            }
+        }
+
+        Commands::RestoreApp { name } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut app_config = config
+                .get_app(&name)
+                .with_context(|| format!("App '{}' not found.", name))?
+                .clone();
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?
+                .clone();
+
+            restore_app(&provider_config, &name, &mut app_config, |app| {
+                config.set_app(name.clone(), app.clone());
+                config.save(cli.config.as_ref())
+            })
+            .await?;
+
+            config.set_app(name, app_config);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::Purge { app } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let now = now_unix();
+
+            let mut names: Vec<String> = match &app {
+                Some(name) => {
+                    let app_config = config
+                        .get_app(name)
+                        .with_context(|| format!("App '{}' not found.", name))?;
+                    if app_config.status != AppStatus::Deleted {
+                        anyhow::bail!("App '{}' is not deleted. Run 'proprion delete-app --name {}' first.", name, name);
+                    }
+                    vec![name.clone()]
+                }
+                None => config
+                    .apps
+                    .iter()
+                    .filter(|(_, a)| a.status == AppStatus::Deleted && a.purge_after.is_some_and(|at| at <= now))
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            };
+            names.sort();
+
+            if names.is_empty() {
+                println!("Nothing to purge.");
+                return Ok(());
+            }
+            let mut revoked = Ok(());
+            for name in &names {
+                let app_config = config.remove_app(name).expect("listed above");
+                println!("Purged '{}'. Its data under {} was left in the bucket.", name, app_config.prefix);
+                if revoked.is_ok() {
+                    revoked = revoke_grants_from(&mut config, name).await;
+                }
+            }
+            config.save(cli.config.as_ref())?;
+            revoked?;
+        }
+
+        Commands::ExportApp { app, to } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?;
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            export_app(provider_config, &app, app_config, &to).await?;
+        }
+
+        Commands::ImportApp { app, from } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?;
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            import_app(provider_config, &app, app_config, &from).await?;
+        }
+
+        Commands::MigrateApp {
+            app,
+            from,
+            to,
+            delete_source,
+            yes,
+        } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let source = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?
+                .clone();
+            if source.provider != from {
+                anyhow::bail!("App '{}' is on provider '{}', not '{}'.", app, source.provider, from);
+            }
+            if from == to {
+                anyhow::bail!("App '{}' is already on provider '{}'.", app, to);
+            }
+            if source.status != AppStatus::Active {
+                anyhow::bail!("App '{}' is {}. Only active apps can be migrated.", app, source.status);
+            }
+            let source_provider = config
+                .get_provider(&from)
+                .with_context(|| format!("Provider '{}' not found.", from))?
+                .clone();
+            let target_provider = config
+                .get_provider(&to)
+                .with_context(|| format!("Provider '{}' not found.", to))?
+                .clone();
+
+            let state_path = Config::path(cli.config.as_ref())?.with_file_name(format!("migrate-{}.json", app));
+            let target = migrate_app(&source_provider, &target_provider, &to, &app, &source, &state_path).await?;
+            for key in &target.keys {
+                print_key_credentials(&target_provider, &app, &target, key)?;
+            }
+
+            config.set_app(app.clone(), target);
+            config.save(cli.config.as_ref())?;
+            std::fs::remove_file(&state_path)
+                .with_context(|| format!("Failed to remove {}", state_path.display()))?;
+            println!("App '{}' now lives on '{}'.", app, to);
+
+            if !delete_source {
+                println!();
+                println!("The app and its data on '{}' were left in place. Once everything uses the new", from);
+                println!("credentials, remove it with: proprion delete-app --provider {} --app-id {}", from, source.id);
+                return Ok(());
+            }
+
+            println!();
+            let versions = list_prefix_versions(&source_provider, &source.prefix)?;
+            print_prefix_versions(&source_provider, &source.prefix, &versions);
+            if !yes && !confirm(&format!("Delete app '{}' and all of this data on '{}'?", app, from))? {
+                println!("Source left in place.");
+                return Ok(());
+            }
+            match &source_provider {
+                ProviderConfig::Scaleway(cfg) => delete_scaleway_app(cfg, &source.id, Some(&app)).await?,
+                ProviderConfig::Exoscale(cfg) => delete_exoscale_app(cfg, &source.id).await?,
+            }
+            wipe_app_data(&source_provider, &app, &source, &versions)?;
+        }
+
+        Commands::Replicate {
+            source,
+            target,
+            mirror_deletes,
+            watch,
+        } => {
+            let config = Config::load(cli.config.as_ref())?;
+            if source == target {
+                anyhow::bail!("Source and target must be different providers.");
+            }
+            let source_provider = config
+                .get_provider(&source)
+                .with_context(|| format!("Provider '{}' not found.", source))?;
+            let target_provider = config
+                .get_provider(&target)
+                .with_context(|| format!("Provider '{}' not found.", target))?;
+            let interval = watch.as_deref().map(parse_duration).transpose()?;
+
+            let state_path = Config::path(cli.config.as_ref())?
+                .with_file_name(format!("replicate-{}-to-{}.json", source, target));
+            loop {
+                let result = replicate(source_provider, target_provider, &state_path, mirror_deletes).await;
+                let Some(interval) = interval else {
+                    result?;
+                    break;
+                };
+                if let Err(err) = result {
+                    eprintln!("Replication failed: {:#}", err);
+                }
+                println!("Next run in {}...", format_duration(interval));
+                println!();
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+            }
+        }
+
+        Commands::Usage { provider, json } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let mut names: Vec<&String> = match &provider {
+                Some(name) => {
+                    config
+                        .get_provider(name)
+                        .with_context(|| format!("Provider '{}' not found.", name))?;
+                    vec![name]
+                }
+                None => config.list_providers(),
+            };
+            names.sort();
+
+            let snapshot_path = Config::path(cli.config.as_ref())?.with_file_name("usage.json");
+            let mut snapshot = load_usage_snapshot(&snapshot_path)?;
+
+            let mut report = serde_json::Map::new();
+            for name in names {
+                let provider_config = &config.providers[name];
+                if !json {
+                    println!("Listing objects in '{}' ({})...", name, provider_config.bucket());
+                }
+                let current = ProviderUsage {
+                    taken_at: now_unix(),
+                    apps: collect_usage(provider_config).await?,
+                };
+                let previous = snapshot.providers.get(name);
+                if json {
+                    report.insert(name.clone(), usage_json(provider_config, &current, previous)?);
+                } else {
+                    print_usage(&current, previous);
+                    println!();
+                }
+                snapshot.providers.insert(name.clone(), current);
+            }
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            std::fs::write(&snapshot_path, serde_json::to_string_pretty(&snapshot)?)
+                .with_context(|| format!("Failed to write {}", snapshot_path.display()))?;
+        }
+
+        Commands::Cost {
+            provider,
+            compare,
+            egress,
+        } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let mut names: Vec<&String> = match &provider {
+                Some(name) => {
+                    config
+                        .get_provider(name)
+                        .with_context(|| format!("Provider '{}' not found.", name))?;
+                    vec![name]
+                }
+                None => config.list_providers(),
+            };
+            names.sort();
+            let egress = egress.as_deref().map(parse_size).transpose()?.unwrap_or(0);
+            let prices = pricing::PriceTable::bundled();
+
+            for name in names {
+                let provider_config = &config.providers[name];
+                println!("Listing objects in '{}' ({})...", name, provider_config.bucket());
+                let usage = collect_usage(provider_config).await?;
+                print_cost(&prices, name, provider_config, &usage, egress);
+                if compare {
+                    print_cost_comparison(&prices, &config, name, &usage, egress);
+                }
+                println!();
+            }
+
+            println!(
+                "Estimates use the bundled price table v{} ({}), excluding VAT.",
+                prices.version, prices.as_of
+            );
+            println!("Check your provider's pricing page for current rates.");
+        }
+
+        Commands::VerifyApp { app, all, key } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let mut names: Vec<&String> = match &app {
+                Some(name) => {
+                    let (name, _) = config
+                        .apps
+                        .get_key_value(name)
+                        .with_context(|| format!("App '{}' not found.", name))?;
+                    vec![name]
+                }
+                None => config
+                    .apps
+                    .iter()
+                    .filter(|(_, a)| matches!(a.status, AppStatus::Active | AppStatus::Suspended))
+                    .map(|(name, _)| name)
+                    .collect(),
+            };
+            names.sort();
+            if all && names.is_empty() {
+                println!("No apps to verify.");
+                return Ok(());
+            }
+
+            let mut failed = Vec::new();
+            for name in names {
+                let app_config = &config.apps[name];
+                let provider_config = config
+                    .get_provider(&app_config.provider)
+                    .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+                if !verify_app(provider_config, name, app_config, key.as_deref()).await? {
+                    failed.push(name.as_str());
+                }
+                println!();
+            }
+
+            if !failed.is_empty() {
+                anyhow::bail!("Isolation check failed for: {}", failed.join(", "));
+            }
+        }
+
+        Commands::Exec { app, key, command } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?;
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            match app_config.status {
+                AppStatus::Active if app_config.expires_in(now_unix()) != Some(0) => {}
+                AppStatus::Active | AppStatus::Expired => {
+                    anyhow::bail!("The keys for app '{}' have expired.", app)
+                }
+                AppStatus::Suspended | AppStatus::Deleted => {
+                    anyhow::bail!("App '{}' is {}.", app, app_config.status)
+                }
+            }
+            let app_key = match &key {
+                Some(label) => app_config
+                    .get_key(label)
+                    .with_context(|| format!("App '{}' has no key labelled '{}'.", app, label))?,
+                None => app_config
+                    .keys
+                    .first()
+                    .with_context(|| format!("App '{}' has no keys.", app))?,
+            };
+            if app_key.revoked {
+                anyhow::bail!(
+                    "Key '{}' of app '{}' was revoked. Issue a new one with 'proprion reissue-keys --app {}'.",
+                    app_key.label,
+                    app,
+                    app
+                );
+            }
+
+            exec_with_app_env(app_config, app_key, provider_config, &command)?;
+        }
+
+        Commands::RotateKey { app, key, grace, finish } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?
+                .clone();
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            if finish {
+                finish_key_rotation(provider_config, &app, &key, &mut app_config).await?;
+            } else {
+                let grace = grace.as_deref().map(parse_duration).transpose()?;
+                rotate_app_key(provider_config, &app, &key, &mut app_config, grace).await?;
+
+                // Save the new key before the old one is revoked, so neither can be lost
+                let provider_config = provider_config.clone();
+                config.set_app(app.clone(), app_config.clone());
+                config.save(cli.config.as_ref())?;
+
+                let old_key = app_config
+                    .get_key(&key)
+                    .and_then(|k| k.retiring.as_ref())
+                    .map(|r| r.access_key.clone())
+                    .context("Key disappeared during rotation")?;
+                if grace.is_none() && confirm(&format!("Revoke the old key {} now?", old_key))? {
+                    finish_key_rotation(&provider_config, &app, &key, &mut app_config).await?;
+                } else {
+                    print_pending_rotation(&app, &key, &app_config);
+                }
+            }
+
+            config.set_app(app, app_config);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::AddKey { app, label } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?
+                .clone();
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            add_app_key(provider_config, &app, &label, &mut app_config).await?;
+
+            config.set_app(app, app_config);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::RevokeKey { app, label } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?
+                .clone();
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            revoke_labelled_key(provider_config, &app, &label, &mut app_config).await?;
+
+            config.set_app(app, app_config);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::UpdateApp {
+            app,
+            access,
+            add_prefix,
+            remove_prefix,
+            quota,
+        } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?
+                .clone();
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            let before = app_config.scopes();
+
+            if let Some(access) = access {
+                app_config.access = access;
+            }
+            for prefix in remove_prefix {
+                let prefix = normalize_prefix(&prefix)?;
+                if !app_config.extra_prefixes.contains(&prefix) {
+                    anyhow::bail!("App '{}' has no extra prefix '{}'.", app, prefix);
+                }
+                app_config.extra_prefixes.retain(|p| *p != prefix);
+            }
+            for prefix in add_prefix {
+                let prefix = normalize_prefix(&prefix)?;
+                if prefix != app_config.prefix && !app_config.extra_prefixes.contains(&prefix) {
+                    app_config.extra_prefixes.push(prefix);
+                }
+            }
+
+            if let Some(quota) = &quota {
+                app_config.quota = match quota.as_str() {
+                    "none" => None,
+                    size => Some(parse_size(size)?),
+                };
+                match app_config.quota {
+                    Some(bytes) => println!(
+                        "Quota of '{}' set to {}. Run 'proprion enforce-quotas' to apply it.",
+                        app,
+                        format_bytes(bytes)
+                    ),
+                    None => {
+                        println!("Quota of '{}' removed.", app);
+                        app_config.over_quota = false;
+                    }
+                }
+            }
+
+            let after = app_config.scopes();
+            if before == after {
+                if quota.is_none() {
+                    println!("No changes to apply.");
+                    return Ok(());
+                }
+                config.set_app(app, app_config);
+                config.save(cli.config.as_ref())?;
+                return Ok(());
+            }
+
+            println!("Updating permissions of app '{}'...", app);
+            apply_app_permissions(provider_config, &app, &app_config).await?;
+            println!();
+            print_permission_diff(provider_config.bucket(), &before, &after);
+
+            config.set_app(app, app_config);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::Grant { from, to, access } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            if from == to {
+                anyhow::bail!("An app cannot be granted access to its own folder.");
+            }
+            let source = config
+                .get_app(&from)
+                .with_context(|| format!("App '{}' not found.", from))?;
+            check_grant_source(&from, source)?;
+            let mut grantee = config
+                .get_app(&to)
+                .with_context(|| format!("App '{}' not found.", to))?
+                .clone();
+            if source.provider != grantee.provider {
+                anyhow::bail!(
+                    "Apps '{}' and '{}' are on different providers and cannot share a folder.",
+                    from,
+                    to
+                );
+            }
+            let provider_config = config
+                .get_provider(&grantee.provider)
+                .with_context(|| format!("Provider '{}' not found.", grantee.provider))?;
+
+            let before = grantee.scopes();
+            let grant = Grant {
+                from: from.clone(),
+                prefix: source.prefix.clone(),
+                access,
+            };
+            grantee.grants.retain(|g| g.from != from);
+            grantee.grants.push(grant);
+
+            println!("Granting '{}' {} access to the folder of '{}'...", to, access, from);
+            apply_app_permissions(provider_config, &to, &grantee).await?;
+            println!();
+            print_permission_diff(provider_config.bucket(), &before, &grantee.scopes());
+
+            config.set_app(to, grantee);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::RevokeGrant { from, to } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut grantee = config
+                .get_app(&to)
+                .with_context(|| format!("App '{}' not found.", to))?
+                .clone();
+            let provider_config = config
+                .get_provider(&grantee.provider)
+                .with_context(|| format!("Provider '{}' not found.", grantee.provider))?;
+
+            if !grantee.grants.iter().any(|g| g.from == from) {
+                anyhow::bail!("App '{}' has no access to the folder of '{}'.", to, from);
+            }
+            let before = grantee.scopes();
+            grantee.grants.retain(|g| g.from != from);
+
+            println!("Revoking access of '{}' to the folder of '{}'...", to, from);
+            apply_app_permissions(provider_config, &to, &grantee).await?;
+            println!();
+            print_permission_diff(provider_config.bucket(), &before, &grantee.scopes());
+
+            config.set_app(to, grantee);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::SuspendApp { app } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?
+                .clone();
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            suspend_app(provider_config, &app, &mut app_config).await?;
+
+            config.set_app(app, app_config);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::ResumeApp { app } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let mut app_config = config
+                .get_app(&app)
+                .with_context(|| format!("App '{}' not found.", app))?
+                .clone();
+            let provider_config = config
+                .get_provider(&app_config.provider)
+                .with_context(|| format!("Provider '{}' not found.", app_config.provider))?;
+
+            resume_app(provider_config, &app, &mut app_config).await?;
+
+            config.set_app(app, app_config);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::RevokeAll { provider, report, yes } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            if let Some(name) = &provider {
+                config
+                    .get_provider(name)
+                    .with_context(|| format!("Provider '{}' not found.", name))?;
+            }
+
+            let scope = match &provider {
+                Some(name) => format!("provider '{}'", name),
+                None => "ALL providers".to_string(),
+            };
+            if !yes && !confirm(&format!("Revoke every proprion-managed API key on {}?", scope))? {
+                println!("Aborted.");
+                return Ok(());
+            }
+
+            let entries = revoke_all_keys(&mut config, provider.as_deref()).await;
+            // Save before anything else can fail, so revoked keys are recorded
+            config.save(cli.config.as_ref())?;
+
+            let report_path = match report {
+                Some(path) => path,
+                None => Config::path(cli.config.as_ref())?
+                    .with_file_name(format!("revoke-all-{}.json", now_unix())),
+            };
+            write_revocation_report(&report_path, &entries)?;
+
+            let failed = entries.iter().filter(|e| e["error"].is_string()).count();
+            println!();
+            println!("Revoked {} key(s), {} failure(s).", entries.len() - failed, failed);
+            println!("Report written to: {}", report_path.display());
+            println!("Issue fresh keys later with: proprion reissue-keys");
+            if failed > 0 {
+                anyhow::bail!("Some keys could not be revoked - check the report and your provider console.");
+            }
+        }
+
+        Commands::ReissueKeys { provider, app } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let result = reissue_revoked_keys(&mut config, provider.as_deref(), app.as_deref()).await;
+            // Save keys that were issued even if others failed
+            config.save(cli.config.as_ref())?;
+            result?;
+        }
+
+        Commands::Adopt {
+            provider,
+            role_id,
+            application_id,
+            name,
+            apply,
+        } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let provider_config = config
+                .get_provider(&provider)
+                .with_context(|| format!("Provider '{}' not found.", provider))?;
+
+            validate_name("app name", &name)?;
+            if config.get_app(&name).is_some() {
+                anyhow::bail!("App '{}' already exists.", name);
+            }
+            let id = match (provider_config, role_id, application_id) {
+                (ProviderConfig::Exoscale(_), Some(id), None) => id,
+                (ProviderConfig::Scaleway(_), None, Some(id)) => id,
+                (ProviderConfig::Exoscale(_), _, _) => anyhow::bail!("Exoscale apps are adopted by --role-id."),
+                (ProviderConfig::Scaleway(_), _, _) => anyhow::bail!("Scaleway apps are adopted by --application-id."),
+            };
+            if let Some((existing, _)) = config.find_app_by_id(&provider, &id) {
+                anyhow::bail!("{} is already recorded as app '{}'.", id, existing);
+            }
+
+            let app = adopt_app(provider_config, &provider, &name, &id, apply).await?;
+            config.set_app(name, app);
+            config.save(cli.config.as_ref())?;
+        }
+
+        Commands::Audit { provider } => {
+            let config = Config::load(cli.config.as_ref())?;
+            let mut names: Vec<&String> = match &provider {
+                Some(name) => {
+                    let (name, _) = config
+                        .providers
+                        .get_key_value(name)
+                        .with_context(|| format!("Provider '{}' not found.", name))?;
+                    vec![name]
+                }
+                None => config.list_providers(),
+            };
+            names.sort();
+
+            let mut differences = 0;
+            for name in names {
+                differences += audit_provider(&config, name, &config.providers[name]).await?;
+                println!();
+            }
+
+            if differences > 0 {
+                anyhow::bail!("Found {} difference(s) between the records and the cloud accounts.", differences);
+            }
+            println!("Records match the cloud accounts.");
+        }
+
+        Commands::EnforceQuotas => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let result = enforce_quotas(&mut config).await;
+            // Save apps that were switched even if others failed
+            config.save(cli.config.as_ref())?;
+            result?;
+        }
+
+        Commands::Gc { dry_run, yes } => {
+            let mut config = Config::load(cli.config.as_ref())?;
+            let result = collect_garbage(&mut config, dry_run).await;
+            // Save the keys that were revoked even if others failed
+            config.save(cli.config.as_ref())?;
+            result?;
+            collect_orphans(&config, dry_run, yes).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Scaleway Implementation
+// ============================================================================
+
+async fn create_scaleway_app(
+    cfg: &ScalewayProviderConfig,
+    provider: &str,
+    name: &str,
+    description: &str,
+    expires_at: Option<u64>,
+    access: AccessMode,
+) -> Result<AppConfig> {
+    let client = scaleway::Client::new(cfg.secret_key.clone());
+    let app_prefix = format!("apps/{}", name);
+
+    println!("Creating app '{}' on Scaleway...", name);
+
+    // Step 1: Create bucket if needed
+    println!("  [1/5] Checking/creating bucket '{}'...", cfg.bucket);
+    ensure_bucket_exists(&cfg.access_key, &cfg.secret_key, &cfg.region, &cfg.endpoint(), &cfg.bucket).await?;
+    println!("        Bucket ready");
+
+    // Step 2: Create application
+    println!("  [2/5] Creating IAM application...");
+    let app = client
+        .create_application(name, description, &cfg.organization_id)
+        .await
+        .context("Failed to create application")?;
+    println!("        Application ID: {}", app.id);
 
     // Step 3: Create policy
-    println!("  [3/5] Creating IAM policy...");
+    println!("  [3/5] Creating IAM policy ({} access)...", access);
     let policy_name = format!("{}-policy", name);
     let policy = client
-        .create_policy(&policy_name, &app.id, &cfg.organization_id, &cfg.project_id)
+        .create_policy(
+            &policy_name,
+            &app.id,
+            &cfg.organization_id,
+            &cfg.project_id,
+            &access.scaleway_permission_sets(),
+        )
         .await
         .context("Failed to create policy")?;
     println!("        Policy ID: {}", policy.id);
 
-    // Step 4: Create API key
-    println!("  [4/5] Creating API key...");
-    let api_key = client
-        .create_api_key(&app.id, &format!("API key for {}", name), Some(&cfg.project_id))
-        .await
-        .context("Failed to create API key")?;
-    println!("        Access Key: {}", api_key.access_key);
+    // Step 4: Create API key
+    println!("  [4/5] Creating API key...");
+    let expires_at_rfc3339 = expires_at.map(format_rfc3339).transpose()?;
+    let api_key = client
+        .create_api_key(
+            &app.id,
+            &format!("API key for {} ({})", name, DEFAULT_KEY_LABEL),
+            Some(&cfg.project_id),
+            expires_at_rfc3339.as_deref(),
+        )
+        .await
+        .context("Failed to create API key")?;
+    println!("        Access Key: {}", api_key.access_key);
+
+    let mut record = AppConfig::new(provider, app.id, description, format!("{}/", app_prefix), access, expires_at);
+    record.keys.push(AppKey {
+        label: DEFAULT_KEY_LABEL.to_string(),
+        access_key: api_key.access_key,
+        secret_key: api_key.secret_key.context("API key response missing secret")?,
+        created_at: Some(now_unix()),
+        retiring: None,
+        revoked: false,
+    });
+
+    // Step 5: Apply bucket policy
+    println!("  [5/5] Applying bucket policy for prefix '{}'...", app_prefix);
+    apply_scaleway_bucket_policy(cfg, name, &record).await?;
+    println!("        Bucket policy applied");
+
+    // Output credentials
+    println!();
+    println!("=== App Created Successfully ===");
+    println!();
+    println!("S3 Credentials for '{}':", name);
+    println!();
+
+    let creds = serde_json::json!({
+        "access_key": record.keys[0].access_key,
+        "secret_key": record.keys[0].secret_key,
+        "endpoint": cfg.endpoint(),
+        "region": cfg.region,
+        "bucket": cfg.bucket,
+        "prefix": app_prefix
+    });
+
+    println!("{}", serde_json::to_string_pretty(&creds)?);
+    println!();
+    println!("IMPORTANT: Save the secret_key now - it cannot be retrieved later!");
+    println!();
+    if let Some(expires) = &expires_at_rfc3339 {
+        println!("This key expires at {}.", expires);
+        println!();
+    }
+    println!("Application ID: {} (save this to delete the app later)", record.id);
+    println!();
+    println!("This app can ONLY access: s3://{}/{}/ ({} access)", cfg.bucket, app_prefix, access);
+
+    Ok(record)
+}
+
+async fn list_scaleway_apps(
+    cfg: &ScalewayProviderConfig,
+    config: &Config,
+    provider: &str,
+    show_keys: bool,
+    show_all: bool,
+) -> Result<()> {
+    let client = scaleway::Client::new(cfg.secret_key.clone());
+
+    println!("Fetching applications...");
+    // Only --all needs the applications proprion didn't create
+    let tag = if show_all { None } else { Some(scaleway::TAG) };
+    let apps = client
+        .list_applications(&cfg.organization_id, tag)
+        .await
+        .context("Failed to list applications")?;
+
+    // Applications made before tagging are still proprion's if they're recorded
+    let (proprion_apps, other_apps): (Vec<_>, Vec<_>) = apps
+        .iter()
+        .partition(|app| app.is_tagged() || config.find_app_by_id(provider, &app.id).is_some());
+
+    // ...but a tagged listing doesn't return them, so take those from the records
+    let mut untagged_records: Vec<(&String, &AppConfig)> = config
+        .apps
+        .iter()
+        .filter(|(_, app)| {
+            app.provider == provider && app.status != AppStatus::Deleted && !apps.iter().any(|a| a.id == app.id)
+        })
+        .collect();
+    untagged_records.sort_by_key(|(name, _)| name.as_str());
+    if show_all {
+        untagged_records.clear();
+    }
+
+    if proprion_apps.is_empty() && untagged_records.is_empty() {
+        println!("No Proprion apps found.");
+    } else {
+        println!();
+        println!("Proprion Apps (Scaleway IAM applications):");
+        for app in proprion_apps {
+            println!("  - {} (ID: {})", app.name, app.id);
+            if let Some(desc) = &app.description {
+                if !desc.is_empty() {
+                    println!("    {}", desc);
+                }
+            }
+            if let Some((name, record)) = config.find_app_by_id(provider, &app.id) {
+                print_app_record(config, name, record, show_keys);
+            }
+        }
+        for (name, record) in untagged_records {
+            println!("  - {} (ID: {})", name, record.id);
+            if !record.description.is_empty() {
+                println!("    {}", record.description);
+            }
+            print_app_record(config, name, record, show_keys);
+        }
+    }
+
+    if !show_all {
+        println!();
+        println!("Applications proprion didn't create are hidden; use --all to list them.");
+    } else if !other_apps.is_empty() {
+        println!();
+        println!("Other applications:");
+        for app in other_apps {
+            println!("  - {} (ID: {})", app.name, app.id);
+            if let Some(desc) = &app.description {
+                if !desc.is_empty() {
+                    println!("    {}", desc);
+                }
+            }
+        }
+    }
+
+    print_deleted_apps(config, provider);
+
+    Ok(())
+}
+
+async fn delete_scaleway_app(cfg: &ScalewayProviderConfig, app_id: &str, app_name: Option<&str>) -> Result<()> {
+    let client = scaleway::Client::new(cfg.secret_key.clone());
+
+    println!("Deleting application {}...", app_id);
+
+    // Just delete the application directly
+    // Scaleway should cascade delete associated resources
+    client
+        .delete_application(app_id)
+        .await
+        .context("Failed to delete application")?;
+
+    println!("Application deleted successfully.");
+
+    match app_name {
+        Some(name) => {
+            println!("Removing bucket policy statements...");
+            edit_scaleway_bucket_policy(cfg, |statements| {
+                statements.retain(|s| !belongs_to_app(s, name, app_id));
+            })
+            .await?;
+        }
+        None => {
+            println!();
+            println!("Note: You may want to manually update the bucket policy to remove this app's statement.");
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Exoscale Implementation
+// ============================================================================
+
+async fn create_exoscale_app(
+    cfg: &ExoscaleProviderConfig,
+    provider: &str,
+    name: &str,
+    description: &str,
+    expires_at: Option<u64>,
+    access: AccessMode,
+) -> Result<AppConfig> {
+    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+    let app_prefix = format!("apps/{}/", name);
+
+    println!("Creating app '{}' on Exoscale...", name);
+
+    // Step 1: Create bucket if needed
+    println!("  [1/3] Checking/creating bucket '{}'...", cfg.bucket);
+    ensure_bucket_exists(&cfg.api_key, &cfg.api_secret, &cfg.zone, &cfg.endpoint(), &cfg.bucket).await?;
+    println!("        Bucket ready");
+
+    // Step 2: Create IAM role with scoped policy
+    println!("  [2/3] Creating IAM role with scoped policy ({} access)...", access);
+    let role_name = format!("proprion-{}", name);
+    let role = client
+        .create_role(&role_name, description, &cfg.bucket, &exoscale_scopes(&[(app_prefix.clone(), access)]))
+        .await
+        .context("Failed to create IAM role")?;
+    println!("        Role ID: {}", role.id);
+
+    // Wait for role to propagate (Exoscale async operations need time)
+    println!("        Waiting for role to propagate...");
+    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+
+    // Step 3: Create API key attached to role
+    println!("  [3/3] Creating API key...");
+    let api_key = client
+        .create_api_key(&exoscale_key_name(name, DEFAULT_KEY_LABEL), &role.id)
+        .await
+        .context("Failed to create API key")?;
+    let access_key = &api_key.key;
+    let secret_key = api_key.secret
+        .as_ref()
+        .context("API key response missing secret")?;
+    println!("        Access Key: {}", access_key);
+
+    // Output credentials
+    println!();
+    println!("=== App Created Successfully ===");
+    println!();
+    println!("S3 Credentials for '{}':", name);
+    println!();
+
+    let creds = serde_json::json!({
+        "access_key": access_key,
+        "secret_key": secret_key,
+        "endpoint": cfg.endpoint(),
+        "zone": cfg.zone,
+        "bucket": cfg.bucket,
+        "prefix": app_prefix
+    });
+
+    println!("{}", serde_json::to_string_pretty(&creds)?);
+    println!();
+    println!("IMPORTANT: Save the secret_key now - it cannot be retrieved later!");
+    println!();
+    if let Some(expires_at) = expires_at {
+        // Exoscale keys have no native expiry - `proprion gc` revokes them
+        println!(
+            "This key expires at {}. Run 'proprion gc' regularly to revoke expired keys.",
+            format_rfc3339(expires_at)?
+        );
+        println!();
+    }
+    println!("Role ID: {} (save this to delete the app later)", role.id);
+    println!();
+    println!("This app can ONLY access: s3://{}/{} ({} access)", cfg.bucket, app_prefix, access);
+
+    let mut record = AppConfig::new(provider, role.id, description, app_prefix, access, expires_at);
+    record.keys.push(AppKey {
+        label: DEFAULT_KEY_LABEL.to_string(),
+        access_key: access_key.clone(),
+        secret_key: secret_key.clone(),
+        created_at: Some(now_unix()),
+        retiring: None,
+        revoked: false,
+    });
+
+    Ok(record)
+}
+
+async fn list_exoscale_apps(
+    cfg: &ExoscaleProviderConfig,
+    config: &Config,
+    provider: &str,
+    show_keys: bool,
+    show_all: bool,
+) -> Result<()> {
+    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+
+    println!("Fetching IAM roles...");
+    let roles = client
+        .list_roles()
+        .await
+        .context("Failed to list roles")?;
+
+    // Filter to only show roles created by Proprion (have "proprion-" prefix) or adopted
+    let (proprion_roles, other_roles): (Vec<_>, Vec<_>) = roles.iter().partition(|r| {
+        r.name
+            .as_ref()
+            .map(|n| n.starts_with("proprion-"))
+            .unwrap_or(false)
+            || config.find_app_by_id(provider, &r.id).is_some()
+    });
+
+    if proprion_roles.is_empty() {
+        println!("No Proprion apps found.");
+    } else {
+        println!();
+        println!("Proprion Apps (Exoscale IAM roles):");
+        for role in proprion_roles {
+            let name = role.name.as_deref().unwrap_or("unknown");
+            let app_name = name.strip_prefix("proprion-").unwrap_or(name);
+            let desc = role.description.as_deref().unwrap_or("");
+            println!("  - {} (Role ID: {})", app_name, role.id);
+            if !desc.is_empty() {
+                println!("    {}", desc);
+            }
+            if let Some((name, record)) = config.find_app_by_id(provider, &role.id) {
+                print_app_record(config, name, record, show_keys);
+            }
+        }
+    }
+
+    if show_all && !other_roles.is_empty() {
+        println!();
+        println!("Other IAM roles:");
+        for role in other_roles {
+            println!("  - {} (Role ID: {})", role.name.as_deref().unwrap_or("unknown"), role.id);
+            if let Some(desc) = role.description.as_deref().filter(|d| !d.is_empty()) {
+                println!("    {}", desc);
+            }
+        }
+    } else if !other_roles.is_empty() {
+        println!();
+        println!("{} other IAM role(s) hidden; use --all to list them.", other_roles.len());
+    }
+
+    print_deleted_apps(config, provider);
+
+    Ok(())
+}
+
+async fn delete_exoscale_app(cfg: &ExoscaleProviderConfig, role_id: &str) -> Result<()> {
+    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+
+    println!("Deleting IAM role {}...", role_id);
+
+    // First, list and delete API keys associated with this role
+    let api_keys = client
+        .list_api_keys()
+        .await
+        .context("Failed to list API keys")?;
+
+    for key in api_keys {
+        if key.role_id.as_deref() == Some(role_id) {
+            println!("  Deleting API key {}...", key.key);
+            client.delete_api_key(&key.key).await.ok();
+        }
+    }
+
+    // Then delete the role
+    client
+        .delete_role(role_id)
+        .await
+        .context("Failed to delete role")?;
+
+    println!("Role and associated API keys deleted successfully.");
+
+    Ok(())
+}
+
+// ============================================================================
+// App Keys
+// ============================================================================
+
+/// Issue a new API key for an existing app.
+async fn issue_app_key(provider: &ProviderConfig, name: &str, label: &str, app: &AppConfig) -> Result<AppKey> {
+    let (access_key, secret_key) = match provider {
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let expires_at = app.expires_at.map(format_rfc3339).transpose()?;
+            let api_key = client
+                .create_api_key(
+                    &app.id,
+                    &format!("API key for {} ({})", name, label),
+                    Some(&cfg.project_id),
+                    expires_at.as_deref(),
+                )
+                .await
+                .context("Failed to create API key")?;
+            let secret_key = api_key.secret_key.context("API key response missing secret")?;
+            (api_key.access_key, secret_key)
+        }
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            let api_key = client
+                .create_api_key(&exoscale_key_name(name, label), &app.id)
+                .await
+                .context("Failed to create API key")?;
+            let secret_key = api_key.secret.context("API key response missing secret")?;
+            (api_key.key, secret_key)
+        }
+    };
+
+    Ok(AppKey {
+        label: label.to_string(),
+        access_key,
+        secret_key,
+        created_at: Some(now_unix()),
+        retiring: None,
+        revoked: false,
+    })
+}
+
+/// Name of an app's Exoscale API key, e.g. `proprion-photo-sync-laptop`.
+fn exoscale_key_name(name: &str, label: &str) -> String {
+    format!("proprion-{}-{}", name, label)
+}
+
+/// Revoke one of an app's API keys. A key that no longer exists counts as revoked.
+async fn revoke_app_key(provider: &ProviderConfig, access_key: &str) -> Result<()> {
+    let (status, err) = match provider {
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            match client.delete_api_key(access_key).await {
+                Ok(()) => return Ok(()),
+                Err(e) => (scaleway_status(&e), anyhow::Error::new(e)),
+            }
+        }
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            match client.delete_api_key(access_key).await {
+                Ok(()) => return Ok(()),
+                Err(e) => (exoscale_status(&e), anyhow::Error::new(e)),
+            }
+        }
+    };
+
+    if status == Some(404) {
+        println!("    API key {} was already deleted", access_key);
+        return Ok(());
+    }
+    Err(err.context(format!("Failed to delete API key {}", access_key)))
+}
+
+/// Print the credentials of a newly issued key.
+fn print_key_credentials(provider: &ProviderConfig, name: &str, app: &AppConfig, key: &AppKey) -> Result<()> {
+    println!();
+    println!("S3 Credentials for '{}' (key '{}'):", name, key.label);
+    println!();
+    let creds = serde_json::json!({
+        "access_key": key.access_key,
+        "secret_key": key.secret_key,
+        "endpoint": provider.endpoint(),
+        "region": provider.region(),
+        "bucket": provider.bucket(),
+        "prefix": app.prefix
+    });
+    println!("{}", serde_json::to_string_pretty(&creds)?);
+    println!();
+    println!("IMPORTANT: Save the secret_key now - it cannot be retrieved later!");
+    println!();
+    Ok(())
+}
+
+async fn add_app_key(provider: &ProviderConfig, name: &str, label: &str, app: &mut AppConfig) -> Result<()> {
+    if app.status != AppStatus::Active {
+        anyhow::bail!("App '{}' is {} and cannot get new keys.", name, app.status);
+    }
+    validate_name("key label", label)?;
+    if app.get_key(label).is_some() {
+        anyhow::bail!("App '{}' already has a key labelled '{}'.", name, label);
+    }
+
+    println!("Creating key '{}' for app '{}'...", label, name);
+    let key = issue_app_key(provider, name, label, app).await?;
+    println!("  Access Key: {}", key.access_key);
+
+    print_key_credentials(provider, name, app, &key)?;
+    app.keys.push(key);
+
+    Ok(())
+}
+
+async fn revoke_labelled_key(provider: &ProviderConfig, name: &str, label: &str, app: &mut AppConfig) -> Result<()> {
+    let key = app
+        .get_key(label)
+        .with_context(|| format!("App '{}' has no key labelled '{}'.", name, label))?;
+
+    println!("Revoking key '{}' ({}) of app '{}'...", label, key.access_key, name);
+    if !key.revoked {
+        revoke_app_key(provider, &key.access_key).await?;
+    }
+    if let Some(retiring) = &key.retiring {
+        println!("  Revoking rotated-out key {}...", retiring.access_key);
+        revoke_app_key(provider, &retiring.access_key).await?;
+    }
+    app.keys.retain(|k| k.label != label);
+    println!("Key revoked. Other keys of '{}' keep working.", name);
+
+    Ok(())
+}
+
+async fn rotate_app_key(
+    provider: &ProviderConfig,
+    name: &str,
+    label: &str,
+    app: &mut AppConfig,
+    grace: Option<u64>,
+) -> Result<()> {
+    if app.status == AppStatus::Expired {
+        anyhow::bail!("App '{}' has expired. Create it again to get new credentials.", name);
+    }
+
+    let key = app
+        .get_key(label)
+        .with_context(|| format!("App '{}' has no key labelled '{}'.", name, label))?;
+    if key.revoked {
+        anyhow::bail!("Key '{}' was revoked. Use 'proprion reissue-keys --app {}' instead.", label, name);
+    }
+    if let Some(retiring) = &key.retiring {
+        anyhow::bail!(
+            "Key {} from a previous rotation is still active. Run 'proprion rotate-key --app {} --key {} --finish' first.",
+            retiring.access_key,
+            name,
+            label
+        );
+    }
+
+    println!("Rotating key '{}' of app '{}'...", label, name);
+    let new_key = issue_app_key(provider, name, label, app).await?;
+    println!("  New Access Key: {}", new_key.access_key);
+    print_key_credentials(provider, name, app, &new_key)?;
+
+    // The old key is retiring until `--finish`, gc, or the caller revokes it
+    let key = app.get_key_mut(label).context("Key disappeared during rotation")?;
+    let old_key = std::mem::replace(key, new_key).access_key;
+    key.retiring = Some(RetiringKey {
+        access_key: old_key,
+        revoke_after: grace.map(|secs| now_unix() + secs),
+    });
+
+    Ok(())
+}
+
+/// Tell the user how long the old key of a rotation stays valid and how to revoke it.
+fn print_pending_rotation(name: &str, label: &str, app: &AppConfig) {
+    let Some(retiring) = app.get_key(label).and_then(|key| key.retiring.as_ref()) else {
+        return;
+    };
+    match retiring.revoke_after {
+        Some(at) => println!(
+            "Old key {} stays valid for {}.",
+            retiring.access_key,
+            format_duration(at.saturating_sub(now_unix()))
+        ),
+        None => println!("Old key {} stays valid.", retiring.access_key),
+    }
+    println!(
+        "Once the app uses the new key, revoke the old one with: proprion rotate-key --app {} --key {} --finish",
+        name, label
+    );
+}
+
+async fn finish_key_rotation(provider: &ProviderConfig, name: &str, label: &str, app: &mut AppConfig) -> Result<()> {
+    let key = app
+        .get_key_mut(label)
+        .with_context(|| format!("App '{}' has no key labelled '{}'.", name, label))?;
+    let retiring = key
+        .retiring
+        .as_ref()
+        .with_context(|| format!("Key '{}' of app '{}' has no rotation in progress.", label, name))?;
+
+    if let Some(revoke_after) = retiring.revoke_after {
+        let now = now_unix();
+        if now < revoke_after {
+            println!(
+                "Note: grace period ends in {}, revoking early.",
+                format_duration(revoke_after - now)
+            );
+        }
+    }
+
+    println!("Revoking old key {}...", retiring.access_key);
+    revoke_app_key(provider, &retiring.access_key).await?;
+    key.retiring = None;
+    println!("Old key revoked. Key rotation for '{}' complete.", name);
+
+    Ok(())
+}
+
+// ============================================================================
+// App Permissions
+// ============================================================================
+
+/// Bring the provider-side permissions of an app in line with its record.
+///
+/// Suspended apps keep their access revoked; the change is applied on resume.
+async fn apply_app_permissions(provider: &ProviderConfig, name: &str, app: &AppConfig) -> Result<()> {
+    match app.status {
+        AppStatus::Suspended => {
+            println!("  App is suspended, changes take effect when it is resumed.");
+            return Ok(());
+        }
+        AppStatus::Deleted => {
+            anyhow::bail!("App '{}' is deleted. Restore it first with 'proprion restore-app {}'.", name, name);
+        }
+        AppStatus::Active | AppStatus::Expired => {}
+    }
+
+    let scopes = app.scopes();
+
+    match provider {
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+
+            // An app left without any access keeps its rules; the bucket policy
+            // no longer lists it, which is what actually blocks it
+            let permission_sets = scaleway_permission_sets(&scopes);
+            if !permission_sets.is_empty() {
+                println!("  Updating IAM policy rules...");
+                let policy = find_scaleway_policy(&client, name, &app.id).await?;
+                client
+                    .set_policy_rules(&policy.id, &cfg.project_id, &permission_sets)
+                    .await
+                    .context("Failed to update policy rules")?;
+            }
+
+            println!("  Updating bucket policy...");
+            apply_scaleway_bucket_policy(cfg, name, app).await?;
+        }
+        ProviderConfig::Exoscale(cfg) => {
+            ensure_editable_role(cfg, name, &app.id).await?;
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+
+            println!("  Updating IAM role policy...");
+            client
+                .update_role_policy(&app.id, &cfg.bucket, &exoscale_scopes(&scopes))
+                .await
+                .context("Failed to update role policy")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fail with a hint when an app's Exoscale role isn't editable, as created by early
+/// versions of proprion. Recreating it means new keys, so it's left to `reissue-keys`.
+async fn ensure_editable_role(cfg: &ExoscaleProviderConfig, name: &str, role_id: &str) -> Result<()> {
+    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+    let roles = client.list_roles().await.context("Failed to list IAM roles")?;
+    let role = roles
+        .iter()
+        .find(|role| role.id == role_id)
+        .with_context(|| format!("IAM role {} of app '{}' no longer exists", role_id, name))?;
+    if role.editable == Some(false) {
+        anyhow::bail!(
+            "The IAM role of app '{}' was created by an early version of proprion and can't be edited.\n\
+             Recreate it with 'proprion reissue-keys --app {}'; the app's keys are reissued with new credentials.",
+            name,
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Replace an Exoscale role that isn't editable with an editable one. API keys can't
+/// move to another role, so each live key is reissued on the new role first; the
+/// record then switches over, and the old keys and role are removed. Old keys that
+/// can't be revoked right away stay recorded as retiring, for `gc`.
+async fn recreate_editable_role(
+    provider: &ProviderConfig,
+    cfg: &ExoscaleProviderConfig,
+    name: &str,
+    app: &mut AppConfig,
+) -> Result<()> {
+    if let Some(key) = app.keys.iter().find(|k| k.retiring.is_some()) {
+        anyhow::bail!(
+            "Finish the rotation of key '{}' first: proprion rotate-key --app {} --key {} --finish",
+            key.label,
+            name,
+            key.label
+        );
+    }
+
+    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+    println!("Recreating the IAM role of '{}' as editable...", name);
+    let role = client
+        .create_role(&format!("proprion-{}", name), &app.description, &cfg.bucket, &exoscale_scopes(&app.scopes()))
+        .await
+        .context("Failed to create IAM role")?;
+    println!("  Role ID: {}", role.id);
+    println!("  Waiting for role to propagate...");
+    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+
+    // Nothing is recorded until every key has a replacement; on failure the new role goes again
+    let mut target = app.clone();
+    target.id = role.id.clone();
+    let mut issued = Vec::new();
+    for key in app.keys.iter().filter(|k| !k.revoked) {
+        println!("  Reissuing key '{}'...", key.label);
+        match issue_app_key(provider, name, &key.label, &target).await {
+            Ok(new_key) => issued.push(new_key),
+            Err(err) => {
+                for new_key in &issued {
+                    if let Err(e) = revoke_app_key(provider, &new_key.access_key).await {
+                        println!("  Warning: {:#}", e);
+                    }
+                }
+                if let Err(e) = client.delete_role(&role.id).await {
+                    println!("  Warning: failed to delete IAM role {}: {}", role.id, e);
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    let old_role = std::mem::replace(&mut app.id, role.id);
+    for new_key in issued {
+        let key = app.get_key_mut(&new_key.label).expect("reissued for this label");
+        let old = std::mem::replace(key, new_key);
+        key.retiring = Some(RetiringKey {
+            access_key: old.access_key,
+            revoke_after: Some(now_unix()),
+        });
+    }
+    for key in app.keys.iter().filter(|k| k.retiring.is_some()) {
+        print_key_credentials(provider, name, app, key)?;
+    }
+
+    let mut failure = None;
+    for key in &mut app.keys {
+        let Some(retiring) = &key.retiring else {
+            continue;
+        };
+        match revoke_app_key(provider, &retiring.access_key).await {
+            Ok(()) => key.retiring = None,
+            Err(err) => {
+                failure.get_or_insert(err);
+            }
+        }
+    }
+    if failure.is_none() {
+        client
+            .delete_role(&old_role)
+            .await
+            .with_context(|| format!("Failed to delete the old IAM role {}", old_role))?;
+    }
+
+    match failure {
+        Some(err) => Err(err.context("Some old keys could not be revoked; 'proprion gc' retries them")),
+        None => Ok(()),
+    }
+}
+
+/// Find the IAM policy proprion created for a Scaleway application.
+async fn find_scaleway_policy(
+    client: &scaleway::Client,
+    name: &str,
+    application_id: &str,
+) -> Result<scaleway::Policy> {
+    let policy_name = format!("{}-policy", name);
+    let mut policies = client
+        .list_policies(application_id, None)
+        .await
+        .context("Failed to list policies")?;
+
+    // Never fall back to another policy: its rules would be overwritten
+    let index = policies
+        .iter()
+        .position(|p| p.name == policy_name)
+        .or_else(|| policies.iter().position(|p| p.is_tagged()))
+        .with_context(|| format!("No IAM policy '{}' found for application {}", policy_name, application_id))?;
+    Ok(policies.swap_remove(index))
+}
+
+/// Union of the Scaleway permission sets needed for all scopes.
+fn scaleway_permission_sets(scopes: &[(String, AccessMode)]) -> Vec<&'static str> {
+    let mut permission_sets = Vec::new();
+    for (_, access) in scopes {
+        for set in access.scaleway_permission_sets() {
+            if !permission_sets.contains(&set) {
+                permission_sets.push(set);
+            }
+        }
+    }
+    permission_sets
+}
+
+/// Cut off all access of an app while keeping its keys, data and record.
+///
+/// Exoscale roles get a deny-all policy. Scaleway applications lose their IAM
+/// policy and bucket-policy statements.
+async fn suspend_app(provider: &ProviderConfig, name: &str, app: &mut AppConfig) -> Result<()> {
+    if app.status != AppStatus::Active {
+        anyhow::bail!("App '{}' is {} and cannot be suspended.", name, app.status);
+    }
+
+    println!("Suspending app '{}'...", name);
+    match provider {
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+
+            // Both steps can be repeated, so a suspend that failed halfway can be retried
+            println!("  Removing bucket policy statements...");
+            edit_scaleway_bucket_policy(cfg, |statements| {
+                statements.retain(|s| !belongs_to_app(s, name, &app.id));
+            })
+            .await?;
+
+            // Every policy of the application, including ones made by hand before `adopt`
+            println!("  Removing IAM policies...");
+            let policies = client
+                .list_policies(&app.id, None)
+                .await
+                .context("Failed to list policies")?;
+            if policies.is_empty() {
+                println!("  IAM policies were already removed");
+            }
+            for policy in &policies {
+                client
+                    .delete_policy(&policy.id)
+                    .await
+                    .with_context(|| format!("Failed to delete policy '{}'", policy.name))?;
+            }
+        }
+        ProviderConfig::Exoscale(cfg) => {
+            ensure_editable_role(cfg, name, &app.id).await?;
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+
+            println!("  Replacing role policy with deny-all...");
+            client
+                .update_role_policy(&app.id, &cfg.bucket, &[])
+                .await
+                .context("Failed to update role policy")?;
+        }
+    }
+
+    app.status = AppStatus::Suspended;
+    println!("App '{}' suspended. Its keys stop working until 'proprion resume-app --app {}'.", name, name);
+    if app.expires_in(now_unix()) == Some(0) {
+        println!("Its keys have also expired; 'proprion gc' revokes them for good.");
+    }
+
+    Ok(())
+}
+
+/// Restore the access of a suspended app.
+async fn resume_app(provider: &ProviderConfig, name: &str, app: &mut AppConfig) -> Result<()> {
+    if app.status != AppStatus::Suspended {
+        anyhow::bail!("App '{}' is not suspended.", name);
+    }
+    if app.expires_in(now_unix()) == Some(0) {
+        anyhow::bail!(
+            "The keys of app '{}' expired while it was suspended. Run 'proprion gc' to revoke them, then create the app again.",
+            name
+        );
+    }
+
+    println!("Resuming app '{}'...", name);
+    app.status = AppStatus::Active;
+
+    if let ProviderConfig::Scaleway(cfg) = provider {
+        // The policy was deleted on suspend; apply_app_permissions expects one, unless
+        // there is nothing to grant. An earlier, failed resume may have created it already.
+        let client = scaleway::Client::new(cfg.secret_key.clone());
+        let permission_sets = scaleway_permission_sets(&app.scopes());
+        let policy_name = format!("{}-policy", name);
+        let policies = client
+            .list_policies(&app.id, None)
+            .await
+            .context("Failed to list policies")?;
+        if !permission_sets.is_empty() && !policies.iter().any(|p| p.name == policy_name || p.is_tagged()) {
+            println!("  Recreating IAM policy...");
+            client
+                .create_policy(&policy_name, &app.id, &cfg.organization_id, &cfg.project_id, &permission_sets)
+                .await
+                .context("Failed to create policy")?;
+        }
+    }
+    apply_app_permissions(provider, name, app).await?;
+
+    println!("App '{}' resumed.", name);
+
+    Ok(())
+}
+
+/// Refuse to share the folder of an app that isn't active: a deleted app's folder
+/// may be purged or reused, and a suspended or expired app's access is on hold.
+fn check_grant_source(name: &str, source: &AppConfig) -> Result<()> {
+    if source.status != AppStatus::Active {
+        anyhow::bail!("App '{}' is {}. Only the folders of active apps can be shared.", name, source.status);
+    }
+    Ok(())
+}
+
+/// Convert (prefix, access mode) pairs into Exoscale role policy scopes.
+fn exoscale_scopes(scopes: &[(String, AccessMode)]) -> Vec<exoscale::PrefixScope> {
+    scopes
+        .iter()
+        .map(|(prefix, access)| exoscale::PrefixScope {
+            prefix: prefix.clone(),
+            operations: access.exoscale_operations(),
+        })
+        .collect()
+}
+
+/// Print effective permissions before and after a change, marking what changed.
+fn print_permission_diff(bucket: &str, before: &[(String, AccessMode)], after: &[(String, AccessMode)]) {
+    println!("Effective permissions:");
+    for scope in before {
+        if !after.contains(scope) {
+            println!("  - s3://{}/{}  {}", bucket, scope.0, scope.1);
+        }
+    }
+    for scope in after {
+        let marker = if before.contains(scope) { " " } else { "+" };
+        println!("  {} s3://{}/{}  {}", marker, bucket, scope.0, scope.1);
+    }
+}
+
+// ============================================================================
+// App Lifecycle
+// ============================================================================
+
+/// Delete an app's IAM objects and keys, keeping its data and a restorable record.
+async fn soft_delete_app(provider: &ProviderConfig, name: &str, app: &mut AppConfig, keep_for: u64) -> Result<()> {
+    match provider {
+        ProviderConfig::Scaleway(cfg) => delete_scaleway_app(cfg, &app.id, Some(name)).await?,
+        ProviderConfig::Exoscale(cfg) => delete_exoscale_app(cfg, &app.id).await?,
+    }
+
+    for key in &mut app.keys {
+        key.revoked = true;
+        key.retiring = None;
+    }
+    app.status = AppStatus::Deleted;
+    app.purge_after = Some(now_unix() + keep_for);
+
+    println!();
+    println!("App '{}' deleted. Its data under {} was kept.", name, app.prefix);
+    println!(
+        "Restore it within {} with: proprion restore-app {}",
+        format_duration(keep_for),
+        name
+    );
+
+    Ok(())
+}
+
+/// Remove the grants other apps have to the folder of `source`, once it is deleted.
+/// Grantees whose permissions could not be updated keep their grant.
+async fn revoke_grants_from(config: &mut Config, source: &str) -> Result<()> {
+    let mut grantees: Vec<String> = config
+        .apps
+        .iter()
+        .filter(|(_, app)| app.grants.iter().any(|g| g.from == source))
+        .map(|(name, _)| name.clone())
+        .collect();
+    grantees.sort();
+
+    for name in grantees {
+        let mut app = config.apps[&name].clone();
+        app.grants.retain(|g| g.from != source);
+        if app.status != AppStatus::Deleted {
+            let provider = config
+                .get_provider(&app.provider)
+                .with_context(|| format!("Provider '{}' not found.", app.provider))?;
+            println!("Revoking access of '{}' to the folder of '{}'...", name, source);
+            apply_app_permissions(provider, &name, &app).await?;
+        }
+        config.set_app(name, app);
+    }
+
+    Ok(())
+}
+
+/// Re-create the IAM objects of a deleted app from its record, with fresh keys
+/// for the same labels and the same prefix, access and grants.
+///
+/// `save` is called after each IAM object is created. A failed restore can be run
+/// again: what already exists on the provider is reused.
+async fn restore_app(
+    provider: &ProviderConfig,
+    name: &str,
+    app: &mut AppConfig,
+    mut save: impl FnMut(&AppConfig) -> Result<()>,
+) -> Result<()> {
+    if app.status != AppStatus::Deleted {
+        anyhow::bail!("App '{}' is not deleted.", name);
+    }
+
+    println!("Restoring app '{}'...", name);
+    let scopes = app.scopes();
+    match provider {
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+
+            let applications = client
+                .list_applications(&cfg.organization_id, None)
+                .await
+                .context("Failed to list applications")?;
+            if !applications.iter().any(|a| a.id == app.id) {
+                println!("  Creating IAM application...");
+                let application = client
+                    .create_application(name, &app.description, &cfg.organization_id)
+                    .await
+                    .context("Failed to create application")?;
+                app.id = application.id;
+                println!("        Application ID: {}", app.id);
+                save(app)?;
+            }
+
+            let permission_sets = scaleway_permission_sets(&scopes);
+            let policies = client
+                .list_policies(&app.id, None)
+                .await
+                .context("Failed to list policies")?;
+            if policies.is_empty() && !permission_sets.is_empty() {
+                println!("  Creating IAM policy...");
+                client
+                    .create_policy(
+                        &format!("{}-policy", name),
+                        &app.id,
+                        &cfg.organization_id,
+                        &cfg.project_id,
+                        &permission_sets,
+                    )
+                    .await
+                    .context("Failed to create policy")?;
+            }
+        }
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+
+            let roles = client.list_roles().await.context("Failed to list IAM roles")?;
+            if !roles.iter().any(|role| role.id == app.id) {
+                println!("  Creating IAM role...");
+                let role = client
+                    .create_role(&format!("proprion-{}", name), &app.description, &cfg.bucket, &exoscale_scopes(&scopes))
+                    .await
+                    .context("Failed to create IAM role")?;
+                app.id = role.id;
+                println!("        Role ID: {}", app.id);
+                save(app)?;
+
+                println!("        Waiting for role to propagate...");
+                tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+            }
+        }
+    }
+
+    // Every key was revoked on delete; one that isn't was issued by an earlier attempt
+    let mut labels: Vec<String> = Vec::new();
+    for key in &app.keys {
+        if !labels.contains(&key.label) {
+            labels.push(key.label.clone());
+        }
+    }
+    if labels.is_empty() {
+        labels.push(DEFAULT_KEY_LABEL.to_string());
+    }
+    for label in labels {
+        if app.keys.iter().any(|k| k.label == label && !k.revoked) {
+            continue;
+        }
+        println!("  Creating key '{}'...", label);
+        let key = issue_app_key(provider, name, &label, app).await?;
+        print_key_credentials(provider, name, app, &key)?;
+        app.keys.retain(|k| k.label != label);
+        app.keys.push(key);
+        save(app)?;
+    }
+
+    if let ProviderConfig::Scaleway(cfg) = provider {
+        println!("  Applying bucket policy...");
+        apply_scaleway_bucket_policy(cfg, name, app).await?;
+    }
+
+    app.status = AppStatus::Active;
+    app.purge_after = None;
+    println!("App '{}' restored with access to {}.", name, app.prefix);
+
+    Ok(())
+}
+
+// ============================================================================
+// App Data
+// ============================================================================
+
+/// Everything stored under a prefix, including old versions and unfinished uploads.
+#[derive(Default)]
+struct PrefixVersions {
+    /// (key, version ID, size) of every object version
+    versions: Vec<(String, String, u64)>,
+    /// (key, version ID) of every delete marker
+    delete_markers: Vec<(String, String)>,
+    /// (key, upload ID) of every unfinished multipart upload
+    uploads: Vec<(String, String)>,
+}
+
+impl PrefixVersions {
+    fn total_bytes(&self) -> u64 {
+        self.versions.iter().map(|(_, _, size)| size).sum()
+    }
+
+    fn len(&self) -> usize {
+        self.versions.len() + self.delete_markers.len() + self.uploads.len()
+    }
+}
+
+/// List every object version, delete marker and unfinished upload under `prefix`,
+/// using the provider's root credentials.
+///
+/// rust-s3 cannot list object versions, so this goes through the aws CLI.
+fn list_prefix_versions(provider: &ProviderConfig, prefix: &str) -> Result<PrefixVersions> {
+    let mut result = PrefixVersions::default();
+
+    let listing = s3api(provider, &["list-object-versions", "--prefix", prefix])?;
+    for version in listing["Versions"].as_array().into_iter().flatten() {
+        if let (Some(key), Some(version_id)) = (version["Key"].as_str(), version["VersionId"].as_str()) {
+            let size = version["Size"].as_u64().unwrap_or(0);
+            result.versions.push((key.to_string(), version_id.to_string(), size));
+        }
+    }
+    for marker in listing["DeleteMarkers"].as_array().into_iter().flatten() {
+        if let (Some(key), Some(version_id)) = (marker["Key"].as_str(), marker["VersionId"].as_str()) {
+            result.delete_markers.push((key.to_string(), version_id.to_string()));
+        }
+    }
+
+    let uploads = s3api(provider, &["list-multipart-uploads", "--prefix", prefix])?;
+    for upload in uploads["Uploads"].as_array().into_iter().flatten() {
+        if let (Some(key), Some(upload_id)) = (upload["Key"].as_str(), upload["UploadId"].as_str()) {
+            result.uploads.push((key.to_string(), upload_id.to_string()));
+        }
+    }
+
+    Ok(result)
+}
+
+fn print_prefix_versions(provider: &ProviderConfig, prefix: &str, versions: &PrefixVersions) {
+    println!(
+        "{}/{} holds {} object version(s) ({}), {} delete marker(s) and {} unfinished upload(s).",
+        provider.bucket(),
+        prefix,
+        versions.versions.len(),
+        format_bytes(versions.total_bytes()),
+        versions.delete_markers.len(),
+        versions.uploads.len()
+    );
+}
+
+/// Permanently delete everything listed in `versions` and print an erasure record.
+fn wipe_app_data(provider: &ProviderConfig, name: &str, app: &AppConfig, versions: &PrefixVersions) -> Result<()> {
+    use std::io::Write;
+
+    if !app.prefix.ends_with('/') || app.prefix.len() < 2 {
+        anyhow::bail!("Refusing to wipe data under unexpected prefix '{}'", app.prefix);
+    }
+
+    let started = now_unix();
+    println!();
+    println!("Erasing data under {}...", app.prefix);
+
+    let objects: Vec<serde_json::Value> = versions
+        .versions
+        .iter()
+        .map(|(key, version_id, _)| (key, version_id))
+        .chain(versions.delete_markers.iter().map(|(key, version_id)| (key, version_id)))
+        .map(|(key, version_id)| serde_json::json!({ "Key": key, "VersionId": version_id }))
+        .collect();
+
+    // DeleteObjects accepts at most 1000 keys per request
+    let mut deleted = 0;
+    for batch in objects.chunks(1000) {
+        let request = serde_json::json!({ "Objects": batch, "Quiet": true });
+        let mut temp_file = tempfile::NamedTempFile::new()?;
+        temp_file.write_all(serde_json::to_string(&request)?.as_bytes())?;
+        let temp_path = temp_file.path().to_string_lossy().to_string();
+
+        let response = s3api(provider, &["delete-objects", "--delete", &format!("file://{}", temp_path)])?;
+        if let Some(errors) = response["Errors"].as_array().filter(|errors| !errors.is_empty()) {
+            anyhow::bail!(
+                "Failed to delete {} object(s), e.g. {}: {}",
+                errors.len(),
+                errors[0]["Key"],
+                errors[0]["Message"]
+            );
+        }
+        deleted += batch.len();
+        println!("  Deleted {}/{}", deleted, objects.len());
+    }
+
+    for (key, upload_id) in &versions.uploads {
+        s3api(provider, &["abort-multipart-upload", "--key", key, "--upload-id", upload_id])?;
+    }
+    if !versions.uploads.is_empty() {
+        println!("  Aborted {} unfinished upload(s)", versions.uploads.len());
+    }
+
+    // Anything still there was written after the listing, e.g. through a grant
+    let remaining = list_prefix_versions(provider, &app.prefix)?;
+    let finished = now_unix();
+
+    println!();
+    println!("Erasure record");
+    println!("  App:             {}", name);
+    println!("  Provider:        {} ({})", app.provider, provider.region());
+    println!("  Location:        {}/{}", provider.bucket(), app.prefix);
+    println!(
+        "  Object versions: {} ({})",
+        versions.versions.len(),
+        format_bytes(versions.total_bytes())
+    );
+    println!("  Delete markers:  {}", versions.delete_markers.len());
+    println!("  Aborted uploads: {}", versions.uploads.len());
+    println!("  Started:         {}", format_rfc3339(started)?);
+    println!("  Finished:        {}", format_rfc3339(finished)?);
+    println!("  Remaining:       {}", remaining.len());
+
+    if remaining.len() > 0 {
+        anyhow::bail!(
+            "{} item(s) are still stored under {}. Run 'proprion delete-app --name {} --wipe-data' again.",
+            remaining.len(),
+            app.prefix,
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// Stream every object under the app's prefix into an archive at `path`.
+async fn export_app(provider: &ProviderConfig, name: &str, app: &AppConfig, path: &std::path::Path) -> Result<()> {
+    use std::io::Seek;
+
+    let bucket = provider_bucket(provider)?;
+
+    println!("Listing objects under {}...", app.prefix);
+    let objects = list_prefix_objects(&bucket, &app.prefix).await?;
+
+    let unreachable: Vec<&str> = objects
+        .iter()
+        .map(|o| o.key.as_str())
+        .filter(|key| !archive::is_addressable_key(key))
+        .collect();
+    if !unreachable.is_empty() {
+        anyhow::bail!(
+            "Can't export objects whose keys contain '.' or '..' segments: {}",
+            unreachable.join(", ")
+        );
+    }
+
+    let total: u64 = objects.iter().map(|o| o.size).sum();
+    println!("Exporting {} object(s) ({}) to {}...", objects.len(), format_bytes(total), path.display());
+
+    let mut writer = archive::ArchiveWriter::create(path)?;
+    let mut entries = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        let key = &object.key[app.prefix.len()..];
+
+        let (head, status) = bucket
+            .head_object(&object.key)
+            .await
+            .with_context(|| format!("Failed to read metadata of '{}'", object.key))?;
+        if status != 200 {
+            anyhow::bail!("Failed to read metadata of '{}' (status: {})", object.key, status);
+        }
+
+        // Objects are buffered on disk, since tar needs each entry's size up front
+        let mut temp_file = tokio::fs::File::from_std(tempfile::tempfile()?);
+        let status = bucket
+            .get_object_to_writer(&object.key, &mut temp_file)
+            .await
+            .with_context(|| format!("Failed to download '{}'", object.key))?;
+        if status != 200 {
+            anyhow::bail!("Failed to download '{}' (status: {})", object.key, status);
+        }
+        let mut temp_file = temp_file.into_std().await;
+        let size = temp_file.stream_position()?;
+        temp_file.rewind()?;
+
+        writer.append_object(entries.len(), size, temp_file)?;
+        entries.push(archive::ManifestEntry {
+            key: key.to_string(),
+            size,
+            etag: head.e_tag.or_else(|| object.e_tag.clone()),
+            content_type: head.content_type,
+            last_modified: Some(object.last_modified.clone()),
+        });
+        println!("  [{}/{}] {}", i + 1, objects.len(), key);
+    }
+
+    writer.finish(&archive::Manifest {
+        format: archive::FORMAT_VERSION,
+        app: name.to_string(),
+        prefix: app.prefix.clone(),
+        exported_at: format_rfc3339(now_unix())?,
+        objects: entries,
+    })?;
+
+    println!();
+    println!("Exported {} object(s) of '{}' to {}", objects.len(), name, path.display());
+
+    Ok(())
+}
+
+/// Upload every object of an archive into the app's prefix, overwriting objects
+/// with the same key.
+async fn import_app(provider: &ProviderConfig, name: &str, app: &AppConfig, path: &std::path::Path) -> Result<()> {
+    use std::io::Seek;
+
+    let manifest = archive::read_manifest(path)?;
+    let total: u64 = manifest.objects.iter().map(|o| o.size).sum();
+    println!(
+        "Importing {} object(s) ({}) exported from '{}' on {} into {}...",
+        manifest.objects.len(),
+        format_bytes(total),
+        manifest.app,
+        manifest.exported_at,
+        app.prefix
+    );
+
+    let bucket = provider_bucket(provider)?;
+    let mut archive = archive::open(path)?;
+    let mut imported = 0;
+    let mut seen = vec![false; manifest.objects.len()];
+    for entry in archive.entries().context("Failed to read archive")? {
+        let mut entry = entry.context("Failed to read archive")?;
+        let Some(index) = archive::object_index(&entry, &manifest)? else {
+            continue;
+        };
+        let object = &manifest.objects[index];
+        let key = &object.key;
+        if std::mem::replace(&mut seen[index], true) {
+            anyhow::bail!("'{}' is in the archive twice", key);
+        }
+
+        let mut temp_file = tempfile::tempfile()?;
+        let size = std::io::copy(&mut entry, &mut temp_file)?;
+        if size != object.size {
+            anyhow::bail!("'{}' is {} bytes in the archive but {} in its manifest", key, size, object.size);
+        }
+        temp_file.rewind()?;
+
+        let target = format!("{}{}", app.prefix, key);
+        let content_type = object.content_type.as_deref().unwrap_or("application/octet-stream");
+        let response = bucket
+            .put_object_stream_with_content_type(&mut tokio::fs::File::from_std(temp_file), &target, content_type)
+            .await
+            .with_context(|| format!("Failed to upload '{}'", target))?;
+        if response.status_code() != 200 {
+            anyhow::bail!("Failed to upload '{}' (status: {})", target, response.status_code());
+        }
+
+        imported += 1;
+        println!("  [{}/{}] {}", imported, manifest.objects.len(), key);
+    }
+
+    if imported != manifest.objects.len() {
+        anyhow::bail!(
+            "Archive is incomplete: imported {} of {} object(s) listed in its manifest",
+            imported,
+            manifest.objects.len()
+        );
+    }
+
+    println!();
+    println!("Imported {} object(s) into '{}'", imported, name);
+
+    Ok(())
+}
+
+// ============================================================================
+// App Migration
+// ============================================================================
+
+/// Progress of a `migrate-app` run, kept next to the config file until it finishes.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MigrationState {
+    /// Provider the app is moving to
+    to: String,
+    /// Record of the app created there
+    target: AppConfig,
+    /// Keys (relative to the prefix) already copied, with their source ETag
+    copied: std::collections::HashMap<String, String>,
+}
+
+/// Re-create an app on another provider and copy its objects there.
+///
+/// Returns the record of the new app once every object has been copied and verified.
+async fn migrate_app(
+    source_provider: &ProviderConfig,
+    target_provider: &ProviderConfig,
+    target_name: &str,
+    name: &str,
+    app: &AppConfig,
+    state_path: &std::path::Path,
+) -> Result<AppConfig> {
+    let mut state = match std::fs::read_to_string(state_path) {
+        Ok(content) => {
+            let state: MigrationState = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", state_path.display()))?;
+            if state.to != target_name {
+                anyhow::bail!(
+                    "An unfinished migration of '{}' to '{}' exists. Finish it, or delete {} to start over.",
+                    name,
+                    state.to,
+                    state_path.display()
+                );
+            }
+            println!(
+                "Resuming migration of '{}' to '{}' ({} object(s) already copied)...",
+                name,
+                target_name,
+                state.copied.len()
+            );
+            state
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            if !app.extra_prefixes.is_empty() || !app.grants.is_empty() {
+                println!("Note: extra prefixes and grants of '{}' are not migrated.", name);
+            }
+
+            // The IAM objects are created once the state is saved, so they're never created twice
+            let mut target = AppConfig::new(
+                target_name,
+                String::new(),
+                &app.description,
+                app.prefix.clone(),
+                app.access,
+                app.expires_at,
+            );
+            target.quota = app.quota;
+            let state = MigrationState {
+                to: target_name.to_string(),
+                target,
+                copied: std::collections::HashMap::new(),
+            };
+            save_migration_state(state_path, &state)?;
+            state
+        }
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", state_path.display())),
+    };
+    provision_migration_target(target_provider, name, app, &mut state, state_path).await?;
+
+    let source_bucket = provider_bucket(source_provider)?;
+    let target_bucket = provider_bucket(target_provider)?;
+
+    println!();
+    println!("Listing objects under {}...", app.prefix);
+    let objects = list_prefix_objects(&source_bucket, &app.prefix).await?;
+    let unreachable: Vec<&str> = objects
+        .iter()
+        .map(|o| o.key.as_str())
+        .filter(|key| !archive::is_addressable_key(key))
+        .collect();
+    if !unreachable.is_empty() {
+        anyhow::bail!(
+            "Can't migrate objects whose keys contain '.' or '..' segments: {}",
+            unreachable.join(", ")
+        );
+    }
+    let total: u64 = objects.iter().map(|o| o.size).sum();
+    println!("Copying {} object(s) ({}) to '{}'...", objects.len(), format_bytes(total), target_name);
+
+    for (i, object) in objects.iter().enumerate() {
+        let key = &object.key[app.prefix.len()..];
+        let etag = object.e_tag.clone().unwrap_or_default();
+        if state.copied.get(key) == Some(&etag) {
+            continue;
+        }
+
+        let target_key = format!("{}{}", state.target.prefix, key);
+        copy_object(&source_bucket, &object.key, &target_bucket, &target_key).await?;
+        state.copied.insert(key.to_string(), etag);
+        save_migration_state(state_path, &state)?;
+        println!("  [{}/{}] {} ({})", i + 1, objects.len(), key, format_bytes(object.size));
+    }
+
+    println!("Verifying copies...");
+    let copies: std::collections::HashMap<String, u64> = list_prefix_objects(&target_bucket, &state.target.prefix)
+        .await?
+        .into_iter()
+        .map(|o| (o.key[state.target.prefix.len()..].to_string(), o.size))
+        .collect();
+    let mismatched: Vec<&str> = objects
+        .iter()
+        .map(|o| (&o.key[app.prefix.len()..], o.size))
+        .filter(|(key, size)| copies.get(*key) != Some(size))
+        .map(|(key, _)| key)
+        .collect();
+    if !mismatched.is_empty() {
+        // Copy these again on the next run
+        for key in &mismatched {
+            state.copied.remove(*key);
+        }
+        save_migration_state(state_path, &state)?;
+        anyhow::bail!(
+            "{} object(s) are missing or differ on '{}', e.g. '{}'. Run the migration again to retry.",
+            mismatched.len(),
+            target_name,
+            mismatched[0]
+        );
+    }
+    println!("All {} object(s) verified.", objects.len());
+
+    Ok(state.target)
+}
+
+/// Create the IAM objects of the migrated app, skipping what an earlier run already
+/// created. The state is saved after each step.
+async fn provision_migration_target(
+    provider: &ProviderConfig,
+    name: &str,
+    app: &AppConfig,
+    state: &mut MigrationState,
+    state_path: &std::path::Path,
+) -> Result<()> {
+    println!("Setting up app '{}' on '{}'...", name, state.to);
+    let (access_key, secret_key) = provider.credentials();
+    ensure_bucket_exists(access_key, secret_key, provider.region(), &provider.endpoint(), provider.bucket()).await?;
+
+    match provider {
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            if state.target.id.is_empty() {
+                println!("  Creating IAM application...");
+                let application = client
+                    .create_application(name, &state.target.description, &cfg.organization_id)
+                    .await
+                    .context("Failed to create application")?;
+                state.target.id = application.id;
+                save_migration_state(state_path, state)?;
+            }
+
+            let policies = client
+                .list_policies(&state.target.id, None)
+                .await
+                .context("Failed to list policies")?;
+            if policies.is_empty() {
+                println!("  Creating IAM policy...");
+                client
+                    .create_policy(
+                        &format!("{}-policy", name),
+                        &state.target.id,
+                        &cfg.organization_id,
+                        &cfg.project_id,
+                        &scaleway_permission_sets(&state.target.scopes()),
+                    )
+                    .await
+                    .context("Failed to create policy")?;
+            }
+        }
+        ProviderConfig::Exoscale(cfg) => {
+            if state.target.id.is_empty() {
+                let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+                println!("  Creating IAM role...");
+                let role = client
+                    .create_role(
+                        &format!("proprion-{}", name),
+                        &state.target.description,
+                        &cfg.bucket,
+                        &exoscale_scopes(&state.target.scopes()),
+                    )
+                    .await
+                    .context("Failed to create IAM role")?;
+                state.target.id = role.id;
+                save_migration_state(state_path, state)?;
+
+                println!("        Waiting for role to propagate...");
+                tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+            }
+        }
+    }
+
+    // Give every device its key back under the same label
+    let mut labels: Vec<&str> = app.keys.iter().filter(|k| !k.revoked).map(|k| k.label.as_str()).collect();
+    if labels.is_empty() {
+        labels.push(DEFAULT_KEY_LABEL);
+    }
+    for label in labels {
+        if state.target.get_key(label).is_none() {
+            println!("  Creating key '{}'...", label);
+            let key = issue_app_key(provider, name, label, &state.target).await?;
+            state.target.keys.push(key);
+            save_migration_state(state_path, state)?;
+        }
+    }
+
+    if let ProviderConfig::Scaleway(cfg) = provider {
+        println!("  Applying bucket policy...");
+        apply_scaleway_bucket_policy(cfg, name, &state.target).await?;
+    }
+
+    Ok(())
+}
+
+/// Write the migration state with owner-only permissions, as it holds the new app's keys.
+fn save_migration_state(path: &std::path::Path, state: &MigrationState) -> Result<()> {
+    config::write_private(path, serde_json::to_string_pretty(state)?.as_bytes())
+}
+
+// ============================================================================
+// Replication
+// ============================================================================
+
+/// Prefix holding every app's folder
+const APPS_PREFIX: &str = "apps/";
+
+/// What `replicate` copied last time, so unchanged objects can be skipped.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct ReplicationState {
+    /// Source ETag and size of each object as of its last copy
+    objects: std::collections::HashMap<String, ReplicatedObject>,
+}
+
+#[derive(PartialEq, serde::Serialize, serde::Deserialize)]
+struct ReplicatedObject {
+    etag: String,
+    size: u64,
+}
+
+/// Copy new and changed objects under `apps/` from the source bucket to the target.
+///
+/// An object is copied when its source ETag or size changed since the last run,
+/// or when the target copy is missing or has a different size. ETags are only
+/// compared against the source, as providers compute multipart ETags differently.
+async fn replicate(
+    source_provider: &ProviderConfig,
+    target_provider: &ProviderConfig,
+    state_path: &std::path::Path,
+    mirror_deletes: bool,
+) -> Result<()> {
+    let mut state: ReplicationState = match std::fs::read_to_string(state_path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", state_path.display()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => ReplicationState::default(),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", state_path.display())),
+    };
+
+    // Whatever was copied is recorded, also when a copy or delete fails
+    let result = replicate_objects(source_provider, target_provider, state_path, &mut state, mirror_deletes).await;
+    save_replication_state(state_path, &state)?;
+    let (copied, deleted) = result?;
+    println!("Replication done: {} copied, {} deleted.", copied, deleted);
+
+    Ok(())
+}
+
+/// Copy new and changed objects, recording each in `state`. Returns the number
+/// of objects copied and deleted.
+async fn replicate_objects(
+    source_provider: &ProviderConfig,
+    target_provider: &ProviderConfig,
+    state_path: &std::path::Path,
+    state: &mut ReplicationState,
+    mirror_deletes: bool,
+) -> Result<(usize, usize)> {
+    let source_bucket = provider_bucket(source_provider)?;
+    let target_bucket = provider_bucket(target_provider)?;
+
+    println!(
+        "Comparing {}/{} with {}/{}...",
+        source_provider.bucket(),
+        APPS_PREFIX,
+        target_provider.bucket(),
+        APPS_PREFIX
+    );
+    let sources = list_prefix_objects(&source_bucket, APPS_PREFIX).await?;
+    let unreachable: Vec<&str> = sources
+        .iter()
+        .map(|o| o.key.as_str())
+        .filter(|key| !archive::is_addressable_key(key))
+        .collect();
+    if !unreachable.is_empty() {
+        anyhow::bail!(
+            "Can't replicate objects whose keys contain '.' or '..' segments: {}",
+            unreachable.join(", ")
+        );
+    }
+    let targets: std::collections::HashMap<String, u64> = list_prefix_objects(&target_bucket, APPS_PREFIX)
+        .await?
+        .into_iter()
+        .map(|o| (o.key, o.size))
+        .collect();
+
+    let pending: Vec<&s3::serde_types::Object> = sources
+        .iter()
+        .filter(|o| {
+            let current = ReplicatedObject {
+                etag: o.e_tag.clone().unwrap_or_default(),
+                size: o.size,
+            };
+            state.objects.get(&o.key) != Some(&current) || targets.get(&o.key) != Some(&o.size)
+        })
+        .collect();
+    let pending_bytes: u64 = pending.iter().map(|o| o.size).sum();
+    println!(
+        "{} object(s) to copy ({}), {} unchanged.",
+        pending.len(),
+        format_bytes(pending_bytes),
+        sources.len() - pending.len()
+    );
+
+    for (i, object) in pending.iter().enumerate() {
+        copy_object(&source_bucket, &object.key, &target_bucket, &object.key).await?;
+        state.objects.insert(
+            object.key.clone(),
+            ReplicatedObject {
+                etag: object.e_tag.clone().unwrap_or_default(),
+                size: object.size,
+            },
+        );
+        println!("  [{}/{}] {} ({})", i + 1, pending.len(), object.key, format_bytes(object.size));
+
+        // Checkpoint regularly, so even a killed run doesn't start from scratch
+        if (i + 1) % 100 == 0 {
+            save_replication_state(state_path, state)?;
+        }
+    }
+
+    // Objects this job copied earlier that no longer exist on the source
+    let source_keys: std::collections::HashSet<&str> = sources.iter().map(|o| o.key.as_str()).collect();
+    let mut removed: Vec<String> = state
+        .objects
+        .keys()
+        .filter(|key| !source_keys.contains(key.as_str()))
+        .cloned()
+        .collect();
+    removed.sort();
+
+    if mirror_deletes {
+        for key in &removed {
+            if targets.contains_key(key) {
+                let response = target_bucket
+                    .delete_object(key)
+                    .await
+                    .with_context(|| format!("Failed to delete '{}'", key))?;
+                if response.status_code() >= 300 {
+                    anyhow::bail!("Failed to delete '{}' (status: {})", key, response.status_code());
+                }
+                println!("  Deleted {}", key);
+            }
+            state.objects.remove(key);
+        }
+    } else if !removed.is_empty() {
+        println!(
+            "{} object(s) were deleted on the source but kept on the target (use --mirror-deletes to delete them).",
+            removed.len()
+        );
+    }
+
+    Ok((pending.len(), if mirror_deletes { removed.len() } else { 0 }))
+}
+
+fn save_replication_state(path: &std::path::Path, state: &ReplicationState) -> Result<()> {
+    std::fs::write(path, serde_json::to_string(state)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// ============================================================================
+// Storage Usage
+// ============================================================================
+
+/// Usage measured by `proprion usage`, kept to show growth on the next run
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct UsageSnapshot {
+    providers: std::collections::HashMap<String, ProviderUsage>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ProviderUsage {
+    /// Unix timestamp of the measurement
+    taken_at: u64,
+    /// Usage per app folder name
+    apps: std::collections::BTreeMap<String, AppUsage>,
+}
+
+/// What is stored under one app's prefix
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct AppUsage {
+    objects: u64,
+    bytes: u64,
+    /// Most recent object modification, as reported by S3
+    last_modified: Option<String>,
+    /// Bytes per S3 storage class
+    #[serde(default)]
+    storage_classes: std::collections::BTreeMap<String, u64>,
+}
+
+/// Sum up the objects under `apps/` per app folder.
+async fn collect_usage(provider: &ProviderConfig) -> Result<std::collections::BTreeMap<String, AppUsage>> {
+    let bucket = provider_bucket(provider)?;
+    let mut usage: std::collections::BTreeMap<String, AppUsage> = std::collections::BTreeMap::new();
+
+    for object in list_prefix_objects(&bucket, APPS_PREFIX).await? {
+        // Objects directly under apps/ belong to no app
+        let Some((app, _)) = object.key[APPS_PREFIX.len()..].split_once('/') else {
+            continue;
+        };
+        let entry = usage.entry(app.to_string()).or_default();
+        entry.objects += 1;
+        entry.bytes += object.size;
+        let class = object.storage_class.as_deref().unwrap_or(pricing::DEFAULT_STORAGE_CLASS);
+        *entry.storage_classes.entry(class.to_string()).or_default() += object.size;
+        // S3 timestamps are ISO 8601 in UTC, so they sort as strings
+        if entry.last_modified.as_deref().is_none_or(|last| last < object.last_modified.as_str()) {
+            entry.last_modified = Some(object.last_modified);
+        }
+    }
+
+    Ok(usage)
+}
+
+fn load_usage_snapshot(path: &std::path::Path) -> Result<UsageSnapshot> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(UsageSnapshot::default()),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn print_usage(current: &ProviderUsage, previous: Option<&ProviderUsage>) {
+    if current.apps.is_empty() {
+        println!("No app data stored.");
+        return;
+    }
+
+    println!();
+    println!("  {:<24} {:>9} {:>11} {:>12}  LAST MODIFIED", "APP", "OBJECTS", "SIZE", "CHANGE");
+    for (name, usage) in &current.apps {
+        let change = match previous {
+            Some(previous) => match previous.apps.get(name) {
+                Some(before) => format_bytes_change(before.bytes, usage.bytes),
+                None => "new".to_string(),
+            },
+            None => String::new(),
+        };
+        let last_modified: String = usage
+            .last_modified
+            .as_deref()
+            .map(|t| t.replace('T', " ").chars().take(16).collect())
+            .unwrap_or_default();
+        println!(
+            "  {:<24} {:>9} {:>11} {:>12}  {}",
+            name,
+            usage.objects,
+            format_bytes(usage.bytes),
+            change,
+            last_modified
+        );
+    }
+
+    let objects: u64 = current.apps.values().map(|u| u.objects).sum();
+    let bytes: u64 = current.apps.values().map(|u| u.bytes).sum();
+    let change = previous
+        .map(|p| format_bytes_change(p.apps.values().map(|u| u.bytes).sum(), bytes))
+        .unwrap_or_default();
+    let total = format!("  {:<24} {:>9} {:>11} {:>12}", "total", objects, format_bytes(bytes), change);
+    println!("{}", total.trim_end());
+
+    if let Some(previous) = previous {
+        println!();
+        println!("Changes are since {}.", format_rfc3339(previous.taken_at).unwrap_or_default());
+    }
+}
+
+fn usage_json(
+    provider: &ProviderConfig,
+    current: &ProviderUsage,
+    previous: Option<&ProviderUsage>,
+) -> Result<serde_json::Value> {
+    let apps: serde_json::Map<String, serde_json::Value> = current
+        .apps
+        .iter()
+        .map(|(name, usage)| {
+            let before = previous.and_then(|p| p.apps.get(name));
+            let entry = serde_json::json!({
+                "objects": usage.objects,
+                "bytes": usage.bytes,
+                "last_modified": usage.last_modified,
+                "bytes_change": before.map(|b| usage.bytes as i64 - b.bytes as i64),
+            });
+            (name.clone(), entry)
+        })
+        .collect();
+
+    Ok(serde_json::json!({
+        "bucket": provider.bucket(),
+        "measured_at": format_rfc3339(current.taken_at)?,
+        "previous_measured_at": previous.map(|p| format_rfc3339(p.taken_at)).transpose()?,
+        "apps": apps,
+    }))
+}
+
+/// Format the difference between two byte counts, e.g. +1.5 MiB.
+fn format_bytes_change(before: u64, after: u64) -> String {
+    match after.cmp(&before) {
+        std::cmp::Ordering::Equal => "no change".to_string(),
+        std::cmp::Ordering::Greater => format!("+{}", format_bytes(after - before)),
+        std::cmp::Ordering::Less => format!("-{}", format_bytes(before - after)),
+    }
+}
+
+/// Switch apps over their quota to read-only, and give write access back to
+/// over-quota apps that are under their limit again.
+///
+/// Every app is checked even if switching another fails; the first error is returned.
+async fn enforce_quotas(config: &mut Config) -> Result<()> {
+    let mut names: Vec<String> = config
+        .apps
+        .iter()
+        .filter(|(_, app)| (app.quota.is_some() || app.over_quota) && app.status != AppStatus::Deleted)
+        .map(|(name, _)| name.clone())
+        .collect();
+    if names.is_empty() {
+        println!("No apps have a quota. Set one with 'proprion create-app --quota' or 'proprion update-app --quota'.");
+        return Ok(());
+    }
+    names.sort();
+
+    let mut objects_by_provider = std::collections::HashMap::new();
+    let mut failure = None;
+    for name in names {
+        let mut app = config.apps[&name].clone();
+        let Some(provider) = config.get_provider(&app.provider) else {
+            println!("  {:<24} provider '{}' not found", name, app.provider);
+            continue;
+        };
+
+        if !objects_by_provider.contains_key(&app.provider) {
+            println!("Measuring usage on '{}'...", app.provider);
+            let bucket = provider_bucket(provider)?;
+            objects_by_provider.insert(app.provider.clone(), list_prefix_objects(&bucket, "").await?);
+        }
+        let used = scopes_usage(&app, &objects_by_provider[&app.provider]);
+
+        let over = app.quota.is_some_and(|quota| used > quota);
+        let limit = app.quota.map(format_bytes).unwrap_or_else(|| "no quota".to_string());
+        let usage = format!("{} / {}", format_bytes(used), limit);
+        if over == app.over_quota {
+            let state = if over { "over quota, read-only" } else { "ok" };
+            println!("  {:<24} {:>24}  {}", name, usage, state);
+            continue;
+        }
+
+        app.over_quota = over;
+        match apply_app_permissions(provider, &name, &app).await {
+            Ok(()) => {
+                let change = if over { "over quota - made read-only" } else { "under quota - write access restored" };
+                println!("  {:<24} {:>24}  {}", name, usage, change);
+                config.set_app(name, app);
+            }
+            Err(err) => {
+                println!("  {:<24} {:>24}  failed: {:#}", name, usage, err);
+                failure.get_or_insert(err);
+            }
+        }
+    }
+
+    match failure {
+        Some(err) => Err(err.context("Some apps could not be updated")),
+        None => Ok(()),
+    }
+}
+
+/// Bytes stored under the prefixes the app can access when it's under its quota.
+/// Objects covered by several (nested) prefixes are counted once.
+fn scopes_usage(app: &AppConfig, objects: &[s3::serde_types::Object]) -> u64 {
+    // Over-quota scopes are downgraded or dropped, so measure the full ones
+    let mut full = app.clone();
+    full.over_quota = false;
+    let prefixes: Vec<String> = full.scopes().into_iter().map(|(prefix, _)| prefix).collect();
+    objects
+        .iter()
+        .filter(|object| prefixes.iter().any(|prefix| object.key.starts_with(prefix.as_str())))
+        .map(|object| object.size)
+        .sum()
+}
+
+// ============================================================================
+// Cost Estimation
+// ============================================================================
+
+/// Print the estimated monthly cost of each app on a provider.
+fn print_cost(
+    prices: &pricing::PriceTable,
+    name: &str,
+    provider: &ProviderConfig,
+    usage: &std::collections::BTreeMap<String, AppUsage>,
+    egress: u64,
+) {
+    println!();
+    println!("Estimated monthly cost on '{}' ({}):", name, provider_label(provider));
+    let Some(region) = prices.region(provider) else {
+        println!("  No prices known for region '{}'.", provider.region());
+        return;
+    };
+
+    println!();
+    println!("  {:<24} {:>11} {:>12}", "APP", "SIZE", "COST");
+    let mut total = 0.0;
+    for (app, app_usage) in usage {
+        let cost = region.storage_cost(&app_usage.storage_classes);
+        total += cost;
+        println!(
+            "  {:<24} {:>11} {:>12}",
+            app,
+            format_bytes(app_usage.bytes),
+            format_cost(cost, &prices.currency)
+        );
+    }
+    if egress > 0 {
+        let cost = region.egress_cost(egress);
+        total += cost;
+        println!(
+            "  {:<24} {:>11} {:>12}",
+            "egress",
+            // Billed in decimal GB, so show it the way it was given
+            format!("{:.0} GB", egress as f64 / 1e9),
+            format_cost(cost, &prices.currency)
+        );
+    }
+    println!("  {:<24} {:>11} {:>12}", "total", "", format_cost(total, &prices.currency));
+
+    if region.requests_per_1000 > 0.0 {
+        println!(
+            "  Plus {} per 1000 requests (not measured).",
+            format_cost(region.requests_per_1000, &prices.currency)
+        );
+    }
+}
+
+/// Print what the same usage would cost at each configured provider.
+fn print_cost_comparison(
+    prices: &pricing::PriceTable,
+    config: &Config,
+    measured: &str,
+    usage: &std::collections::BTreeMap<String, AppUsage>,
+    egress: u64,
+) {
+    let mut bytes_by_class = std::collections::BTreeMap::new();
+    for app_usage in usage.values() {
+        for (class, bytes) in &app_usage.storage_classes {
+            *bytes_by_class.entry(class.clone()).or_insert(0) += bytes;
+        }
+    }
+
+    let mut estimates: Vec<(&String, &ProviderConfig, Option<f64>)> = config
+        .providers
+        .iter()
+        .map(|(name, provider)| {
+            let cost = prices
+                .region(provider)
+                .map(|region| region.storage_cost(&bytes_by_class) + region.egress_cost(egress));
+            (name, provider, cost)
+        })
+        .collect();
+    // Cheapest first, unknown regions last
+    estimates.sort_by(|a, b| {
+        a.2.unwrap_or(f64::INFINITY)
+            .total_cmp(&b.2.unwrap_or(f64::INFINITY))
+            .then(a.0.cmp(b.0))
+    });
+
+    println!();
+    println!("The same usage at each configured provider:");
+    for (name, provider, cost) in estimates {
+        let marker = if name == measured { "*" } else { " " };
+        let label = format!("{} ({})", name, provider_label(provider));
+        let cost = match cost {
+            Some(cost) => format_cost(cost, &prices.currency),
+            None => "no prices".to_string(),
+        };
+        println!("  {} {:<40} {:>12}", marker, label, cost);
+    }
+}
+
+/// Provider type and region, e.g. "scaleway, fr-par".
+fn provider_label(provider: &ProviderConfig) -> String {
+    match provider {
+        ProviderConfig::Scaleway(cfg) => format!("scaleway, {}", cfg.region),
+        ProviderConfig::Exoscale(cfg) => format!("exoscale, {}", cfg.zone),
+    }
+}
+
+fn format_cost(amount: f64, currency: &str) -> String {
+    format!("{:.2} {}", amount, currency)
+}
+
+// ============================================================================
+// Isolation Verification
+// ============================================================================
+
+/// How the provider answered a probe request
+#[derive(Clone, Copy, PartialEq)]
+enum ProbeOutcome {
+    Allowed,
+    Denied,
+    /// Any other status, e.g. a server error
+    Failed(u16),
+}
+
+#[derive(Clone, Copy)]
+enum ProbeOperation {
+    Write,
+    Read,
+    List,
+    Delete,
+}
+
+const PROBE_OPERATIONS: [ProbeOperation; 4] = [
+    ProbeOperation::Write,
+    ProbeOperation::Read,
+    ProbeOperation::List,
+    ProbeOperation::Delete,
+];
+
+/// Probe what an app's key can do inside and outside its folder and print the
+/// matrix. Returns whether every probe matched the app's access mode.
+///
+/// Probe objects are placed and cleaned up with the provider's root credentials,
+/// so read and delete probes work even for apps that cannot write.
+async fn verify_app(provider: &ProviderConfig, name: &str, app: &AppConfig, key: Option<&str>) -> Result<bool> {
+    let app_key = match key {
+        Some(label) => app
+            .get_key(label)
+            .with_context(|| format!("App '{}' has no key labelled '{}'.", name, label))?,
+        None => app
+            .keys
+            .first()
+            .with_context(|| format!("App '{}' has no keys.", name))?,
+    };
+    if app_key.revoked || !matches!(app.status, AppStatus::Active | AppStatus::Suspended) {
+        anyhow::bail!("App '{}' has no usable key to verify with.", name);
+    }
+
+    // What the app should be able to do in its own folder
+    let own_access = match app.status {
+        AppStatus::Active => app.effective_access(app.access),
+        _ => None,
+    };
+    println!(
+        "Verifying isolation of '{}' with key '{}' ({})...",
+        name,
+        app_key.label,
+        match (app.status, own_access) {
+            (AppStatus::Suspended, _) => "suspended, expecting no access".to_string(),
+            (_, Some(access)) => format!("{} access", access),
+            (_, None) => "over quota, expecting no access".to_string(),
+        }
+    );
+
+    let sibling = format!("{}-probe/", app.prefix.trim_end_matches('/'));
+    let areas = [
+        (app.prefix.clone(), app.prefix.clone()),
+        (sibling.clone(), sibling),
+        (String::new(), "bucket root".to_string()),
+    ];
+    let probe_name = format!(".proprion-probe-{}", uuid::Uuid::new_v4());
+
+    let root_bucket = provider_bucket(provider)?;
+    for (prefix, _) in &areas {
+        let key = format!("{}{}", prefix, probe_name);
+        let response = root_bucket
+            .put_object(&key, b"proprion isolation probe")
+            .await
+            .with_context(|| format!("Failed to place probe object '{}'", key))?;
+        if response.status_code() != 200 {
+            anyhow::bail!("Failed to place probe object '{}' (status: {})", key, response.status_code());
+        }
+    }
+
+    let app_bucket = s3_bucket(provider, &app_key.access_key, &app_key.secret_key)?;
+    let http = reqwest::Client::new();
+    let mut results = Vec::new();
+    let mut probe_error = None;
+    'areas: for (area, (prefix, _)) in areas.iter().enumerate() {
+        for operation in PROBE_OPERATIONS {
+            match probe(&http, &app_bucket, operation, prefix, &probe_name).await {
+                Ok(outcome) => results.push((area, operation, outcome)),
+                Err(err) => {
+                    probe_error = Some(err);
+                    break 'areas;
+                }
+            }
+        }
+    }
+
+    // Clean up before reporting, whatever the app managed to do
+    for (prefix, _) in &areas {
+        for key in [format!("{}{}", prefix, probe_name), format!("{}{}-write", prefix, probe_name)] {
+            root_bucket.delete_object(&key).await.ok();
+        }
+    }
+    if let Some(err) = probe_error {
+        return Err(err);
+    }
+
+    println!();
+    println!("  {:<32} {:<10} {:<10} {:<10} DELETE", "", "WRITE", "READ", "LIST");
+    let mut passed = 0;
+    for (area, (_, label)) in areas.iter().enumerate() {
+        let mut row = format!("  {:<32}", label);
+        for (_, operation, outcome) in results.iter().filter(|(a, _, _)| *a == area) {
+            let expected = probe_expected(own_access, area == 0, *operation);
+            let mut cell = match outcome {
+                ProbeOutcome::Allowed => "allowed".to_string(),
+                ProbeOutcome::Denied => "denied".to_string(),
+                ProbeOutcome::Failed(status) => format!("error {}", status),
+            };
+            if *outcome == expected {
+                passed += 1;
+            } else {
+                cell.push('!');
+            }
+            row.push_str(&format!(" {:<10}", cell));
+        }
+        println!("{}", row.trim_end());
+    }
+
+    println!();
+    let total = results.len();
+    if passed == total {
+        println!("  PASS: all {} checks behaved as expected.", total);
+    } else {
+        println!("  FAIL: {} of {} checks did not behave as expected (marked with !).", total - passed, total);
+    }
+
+    Ok(passed == total)
+}
+
+/// Whether a probe should be allowed, given the app's access in its own folder.
+/// Listing another folder counts as a leak, since it reveals object names.
+fn probe_expected(own_access: Option<AccessMode>, own_folder: bool, operation: ProbeOperation) -> ProbeOutcome {
+    let Some(access) = own_access else {
+        return ProbeOutcome::Denied;
+    };
+    let allowed = match operation {
+        ProbeOperation::Write => own_folder && access.can_write(),
+        ProbeOperation::Read => own_folder && access.can_read(),
+        ProbeOperation::List => own_folder && access.can_read(),
+        ProbeOperation::Delete => own_folder && access.can_delete(),
+    };
+    if allowed {
+        ProbeOutcome::Allowed
+    } else {
+        ProbeOutcome::Denied
+    }
+}
+
+/// Try one operation with the app's key through a presigned URL, which gives
+/// the raw status code for every operation, including listing.
+async fn probe(
+    http: &reqwest::Client,
+    bucket: &Bucket,
+    operation: ProbeOperation,
+    prefix: &str,
+    probe_name: &str,
+) -> Result<ProbeOutcome> {
+    let key = format!("{}{}", prefix, probe_name);
+    let request = match operation {
+        ProbeOperation::Write => {
+            let url = bucket.presign_put(format!("{}-write", key), 300, None, None).await?;
+            http.put(url).body("proprion isolation probe")
+        }
+        ProbeOperation::Read => http.get(bucket.presign_get(&key, 300, None).await?),
+        ProbeOperation::List => {
+            let query = std::collections::HashMap::from([
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.to_string()),
+            ]);
+            http.get(bucket.presign_get("/", 300, Some(query)).await?)
+        }
+        ProbeOperation::Delete => http.delete(bucket.presign_delete(&key, 300).await?),
+    };
+
+    let status = request.send().await.context("Probe request failed")?.status();
+    Ok(if status.is_success() {
+        ProbeOutcome::Allowed
+    } else if status == reqwest::StatusCode::FORBIDDEN {
+        ProbeOutcome::Denied
+    } else {
+        ProbeOutcome::Failed(status.as_u16())
+    })
+}
+
+// ============================================================================
+// Diagnostics
+// ============================================================================
+
+/// Clock difference beyond which Exoscale request signatures can't be trusted
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
+/// A misconfiguration found by `doctor`, with what to do about it
+struct Finding {
+    problem: String,
+    fix: String,
+}
+
+impl Finding {
+    fn new(problem: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            problem: problem.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+/// Run every check against a provider, printing the results. Returns the number of problems.
+async fn diagnose_provider(name: &str, provider: &ProviderConfig) -> usize {
+    println!("Checking provider '{}' ({})...", name, provider_label(provider));
+
+    let mut problems = 0;
+    if let ProviderConfig::Exoscale(cfg) = provider {
+        report_check(&mut problems, "Clock", check_clock_skew(cfg).await);
+    }
+    let credentials_ok = report_check(&mut problems, "IAM credentials", check_iam_credentials(name, provider).await);
+    let bucket = check_bucket(provider).await.map(|exists| {
+        if exists {
+            format!("'{}' reachable in {}", provider.bucket(), provider.region())
+        } else {
+            format!("'{}' doesn't exist yet; create-app will create it", provider.bucket())
+        }
+    });
+    report_check(&mut problems, "Bucket", bucket);
+    if credentials_ok {
+        report_check(&mut problems, "IAM permissions", check_iam_create(name, provider).await);
+    } else {
+        println!("  skip  IAM permissions: needs working IAM credentials");
+    }
+
+    problems
+}
+
+/// Check a provider before `add-provider` saves it, optionally creating its bucket.
+async fn validate_provider(name: &str, provider: &ProviderConfig, create_bucket: bool) -> Result<()> {
+    let (region_kind, provider_type) = match provider {
+        ProviderConfig::Scaleway(_) => ("region", "Scaleway"),
+        ProviderConfig::Exoscale(_) => ("zone", "Exoscale"),
+    };
+    if !provider.known_regions().contains(&provider.region()) {
+        anyhow::bail!(
+            "Unknown {} {} '{}'. Known {}s: {}. Pass --skip-validation to save it anyway.",
+            provider_type,
+            region_kind,
+            provider.region(),
+            region_kind,
+            provider.known_regions().join(", ")
+        );
+    }
+
+    println!("Validating provider '{}' ({})...", name, provider_label(provider));
+    let mut problems = 0;
+    report_check(&mut problems, "IAM credentials", check_iam_credentials(name, provider).await);
+
+    report_check(&mut problems, "Bucket", check_or_create_bucket(provider, create_bucket).await);
+
+    if problems > 0 {
+        anyhow::bail!(
+            "Provider '{}' not saved: found {} problem(s). Pass --skip-validation to save it anyway.",
+            name,
+            problems
+        );
+    }
+    Ok(())
+}
+
+/// Check the provider's bucket, creating it when `create` is set and it doesn't exist.
+async fn check_or_create_bucket(provider: &ProviderConfig, create: bool) -> std::result::Result<String, Finding> {
+    match check_bucket(provider).await? {
+        true => Ok(format!("'{}' reachable in {}", provider.bucket(), provider.region())),
+        false if create => {
+            let (access_key, secret_key) = provider.credentials();
+            ensure_bucket_exists(access_key, secret_key, provider.region(), &provider.endpoint(), provider.bucket())
+                .await
+                .map(|()| format!("created '{}' in {}", provider.bucket(), provider.region()))
+                .map_err(|e| {
+                    Finding::new(
+                        format!("{:#}", e),
+                        "Bucket names are global: if the name is taken, choose another one",
+                    )
+                })
+        }
+        false => Err(Finding::new(
+            format!("Bucket '{}' doesn't exist", provider.bucket()),
+            "Pass --create-bucket to create it, or check the name given with --bucket",
+        )),
+    }
+}
+
+/// Print the result of a check. Returns whether it passed.
+fn report_check(problems: &mut usize, check: &str, result: std::result::Result<String, Finding>) -> bool {
+    match result {
+        Ok(detail) => {
+            println!("  ok    {}: {}", check, detail);
+            true
+        }
+        Err(finding) => {
+            *problems += 1;
+            println!("  FAIL  {}: {}", check, finding.problem);
+            println!("        Fix: {}", finding.fix);
+            false
+        }
+    }
+}
+
+/// Compare the local clock with the Exoscale API server's.
+async fn check_clock_skew(cfg: &ExoscaleProviderConfig) -> std::result::Result<String, Finding> {
+    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+    let server = client.server_time().await.map_err(|e| {
+        Finding::new(
+            format!("Couldn't read the API server's clock: {}", e),
+            format!("Check that {} is reachable from this machine", cfg.api_base()),
+        )
+    })?;
+
+    let (skew, direction) = match server.duration_since(std::time::SystemTime::now()) {
+        Ok(behind) => (behind.as_secs(), "behind"),
+        Err(ahead) => (ahead.duration().as_secs(), "ahead of"),
+    };
+    if skew > MAX_CLOCK_SKEW_SECS {
+        return Err(Finding::new(
+            format!(
+                "The local clock is {}s {} the API server's, so Exoscale rejects request signatures as expired",
+                skew, direction
+            ),
+            "Synchronize the system clock, e.g. with `timedatectl set-ntp true`",
+        ));
+    }
+    Ok(format!("within {}s of the API server", skew))
+}
+
+/// Make an authenticated read-only IAM call.
+async fn check_iam_credentials(name: &str, provider: &ProviderConfig) -> std::result::Result<String, Finding> {
+    match provider {
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            let roles = client
+                .list_roles()
+                .await
+                .map_err(|e| iam_finding(name, provider, exoscale_status(&e), e.to_string(), "list IAM roles"))?;
+            Ok(format!("key accepted ({} IAM roles)", roles.len()))
+        }
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let apps = client
+                .list_applications(&cfg.organization_id, None)
+                .await
+                .map_err(|e| iam_finding(name, provider, scaleway_status(&e), e.to_string(), "list IAM applications"))?;
+            Ok(format!("key accepted ({} IAM applications)", apps.len()))
+        }
+    }
+}
+
+/// Check that the bucket is reachable and in the configured region or zone.
+/// Returns whether it exists.
+async fn check_bucket(provider: &ProviderConfig) -> std::result::Result<bool, Finding> {
+    let (region_kind, flag) = match provider {
+        ProviderConfig::Scaleway(_) => ("region", "--region"),
+        ProviderConfig::Exoscale(_) => ("zone", "--zone"),
+    };
+    let name = provider.bucket();
+    let bucket = provider_bucket(provider).map_err(|e| Finding::new(format!("{:#}", e), "Check the bucket name"))?;
+
+    // Bucket::location() mangles the query with path-style buckets, so ask for it directly
+    let query = std::collections::HashMap::from([("location".to_string(), String::new())]);
+    let url = bucket
+        .presign_get("/", 300, Some(query))
+        .await
+        .map_err(|e| Finding::new(format!("Failed to sign request: {}", e), "Check the provider's keys"))?;
+    let unreachable = |e: reqwest::Error| {
+        Finding::new(
+            format!("Couldn't reach {}: {}", provider.endpoint(), e),
+            format!("Check network access and that {} '{}' exists", region_kind, provider.region()),
+        )
+    };
+    let response = reqwest::get(url).await.map_err(unreachable)?;
+    let status = response.status().as_u16();
+    let body = response.text().await.map_err(unreachable)?;
+
+    match status {
+        200 => {
+            // Empty when the provider doesn't report a location constraint
+            let location = xml_text(&body, "LocationConstraint").unwrap_or_default();
+            if location.is_empty() || location == provider.region() {
+                Ok(true)
+            } else {
+                Err(Finding::new(
+                    format!("Bucket '{}' is in {}, not {}", name, location, provider.region()),
+                    format!("Re-add the provider with {} {}", flag, location),
+                ))
+            }
+        }
+        301 => Err(Finding::new(
+            format!("Bucket '{}' exists but not in {} {}", name, region_kind, provider.region()),
+            format!("Re-add the provider with {} set to the bucket's {}", flag, region_kind),
+        )),
+        403 => Err(Finding::new(
+            format!("Access to bucket '{}' is denied", name),
+            format!(
+                "Bucket names are global: if another account owns '{}', re-add the provider with another bucket. \
+                 Otherwise give the provider's key Object Storage access.",
+                name
+            ),
+        )),
+        404 if body.contains("NoSuchBucket") => Ok(false),
+        status => Err(Finding::new(
+            format!("Unexpected response from bucket '{}' (status: {})", name, status),
+            "Check the provider's status page and run doctor again",
+        )),
+    }
+}
+
+/// Create and delete a throwaway IAM role or application, with an API key.
+async fn check_iam_create(name: &str, provider: &ProviderConfig) -> std::result::Result<String, Finding> {
+    let probe_name = format!("proprion-doctor-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let description = "Temporary check by proprion doctor, safe to delete";
+    let mut leftovers = Vec::new();
+
+    match provider {
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            let role = client
+                .create_role(&probe_name, description, &cfg.bucket, &[])
+                .await
+                .map_err(|e| iam_finding(name, provider, exoscale_status(&e), e.to_string(), "create IAM roles"))?;
+
+            let key = client.create_api_key(&probe_name, &role.id).await;
+            if let Ok(key) = &key {
+                if client.delete_api_key(&key.key).await.is_err() {
+                    leftovers.push(format!("API key {}", key.key));
+                }
+            }
+            if client.delete_role(&role.id).await.is_err() {
+                leftovers.push(format!("IAM role {}", role.id));
+            }
+            key.map_err(|e| iam_finding(name, provider, exoscale_status(&e), e.to_string(), "create API keys"))?;
+        }
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let app = client
+                .create_application(&probe_name, description, &cfg.organization_id)
+                .await
+                .map_err(|e| {
+                    iam_finding(name, provider, scaleway_status(&e), e.to_string(), "create IAM applications")
+                })?;
+
+            let key = client.create_api_key(&app.id, description, None, None).await;
+            if let Ok(key) = &key {
+                if client.delete_api_key(&key.access_key).await.is_err() {
+                    leftovers.push(format!("API key {}", key.access_key));
+                }
+            }
+            if client.delete_application(&app.id).await.is_err() {
+                leftovers.push(format!("IAM application {}", app.id));
+            }
+            key.map_err(|e| iam_finding(name, provider, scaleway_status(&e), e.to_string(), "create API keys"))?;
+        }
+    }
+
+    if !leftovers.is_empty() {
+        return Err(Finding::new(
+            format!("Couldn't delete the temporary {}", leftovers.join(" and ")),
+            "Give the key permission to delete IAM objects, and delete the leftovers in the console",
+        ));
+    }
+    Ok("can create and delete IAM objects and API keys".to_string())
+}
+
+/// Explain a failed IAM call, e.g. of `action` "create IAM roles".
+fn iam_finding(name: &str, provider: &ProviderConfig, status: Option<u16>, error: String, action: &str) -> Finding {
+    match (provider, status) {
+        (ProviderConfig::Exoscale(_), Some(401)) => Finding::new(
+            format!("The API key or secret is invalid ({})", error),
+            format!(
+                "Check the key under IAM > API Keys in the Exoscale console, then re-add it with `proprion add-provider exoscale --name {}`",
+                name
+            ),
+        ),
+        (ProviderConfig::Exoscale(_), Some(403)) => Finding::new(
+            format!("The API key is not allowed to {} ({})", action, error),
+            "Use a key whose IAM role allows the iam service, or an unrestricted key",
+        ),
+        (ProviderConfig::Exoscale(cfg), _) => Finding::new(
+            error,
+            format!("Check network access to {} and that zone '{}' exists", cfg.api_base(), cfg.zone),
+        ),
+        (ProviderConfig::Scaleway(_), Some(401)) => Finding::new(
+            format!("The secret key is invalid or expired ({})", error),
+            format!(
+                "Create a new API key under IAM > API keys in the Scaleway console, then re-add it with `proprion add-provider scaleway --name {}`",
+                name
+            ),
+        ),
+        (ProviderConfig::Scaleway(cfg), Some(403)) => Finding::new(
+            format!("The key is not allowed to {} in organization {} ({})", action, cfg.organization_id, error),
+            "Give the key's user or application the IAMManager permission set",
+        ),
+        (ProviderConfig::Scaleway(cfg), _) => Finding::new(
+            error,
+            format!(
+                "Check network access to api.scaleway.com and that organization ID '{}' is correct",
+                cfg.organization_id
+            ),
+        ),
+    }
+}
+
+/// Text of the first `<tag>` element in an XML document, if any.
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = xml.find(&format!("<{}", tag))?;
+    let start = open + xml[open..].find('>')? + 1;
+    if xml[..start].ends_with("/>") {
+        return Some("");
+    }
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(&xml[start..end])
+}
+
+fn exoscale_status(err: &exoscale::ExoscaleError) -> Option<u16> {
+    match err {
+        exoscale::ExoscaleError::Api { status, .. } => Some(*status),
+        _ => None,
+    }
+}
+
+fn scaleway_status(err: &scaleway::ScalewayError) -> Option<u16> {
+    match err {
+        scaleway::ScalewayError::Api { status, .. } => Some(*status),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Interactive Setup
+// ============================================================================
+
+/// Ask for every `add-provider` flag that was left out, checking each step
+/// before moving on to the next.
+async fn provider_wizard(provider: Option<AddProviderCommand>) -> Result<AddProviderCommand> {
+    let provider = match provider {
+        Some(provider) => provider,
+        None => match prompt_choice("Provider", &["Scaleway", "Exoscale"])? {
+            0 => AddProviderCommand::Scaleway {
+                name: None,
+                access_key: None,
+                secret_key: None,
+                region: None,
+                bucket: None,
+                organization_id: None,
+                project_id: None,
+                endpoint: None,
+                create_bucket: false,
+                skip_validation: false,
+            },
+            _ => AddProviderCommand::Exoscale {
+                name: None,
+                api_key: None,
+                api_secret: None,
+                zone: None,
+                bucket: None,
+                endpoint: None,
+                create_bucket: false,
+                skip_validation: false,
+            },
+        },
+    };
+
+    match provider {
+        AddProviderCommand::Scaleway {
+            name,
+            mut access_key,
+            mut secret_key,
+            region,
+            bucket,
+            mut organization_id,
+            project_id,
+            endpoint,
+            create_bucket,
+            skip_validation,
+        } => {
+            let name = or_prompt(name, "Provider name (your choice, e.g., my-scaleway)")?;
+            let region = choose_region(region, "Region", SCALEWAY_REGIONS, skip_validation)?;
+
+            println!("Create an API key under IAM > API keys in the Scaleway console.");
+            let mut cfg = ScalewayProviderConfig {
+                access_key: String::new(),
+                secret_key: String::new(),
+                organization_id: String::new(),
+                project_id: String::new(),
+                region,
+                bucket: String::new(),
+                endpoint,
+            };
+            let mut project_id = project_id;
+            loop {
+                cfg.organization_id = or_prompt(organization_id.take(), "Organization ID")?;
+                if cfg.project_id.is_empty() {
+                    cfg.project_id = or_prompt(project_id.take(), "Project ID")?;
+                }
+                cfg.access_key = or_prompt(access_key.take(), "Access key")?;
+                cfg.secret_key = or_prompt_secret(secret_key.take(), "Secret key")?;
+                let provider = ProviderConfig::Scaleway(cfg.clone());
+                if skip_validation || wizard_check_credentials(&name, &provider).await {
+                    break;
+                }
+            }
+
+            let bucket = choose_bucket(&ProviderConfig::Scaleway(cfg.clone()), bucket, create_bucket, skip_validation).await?;
+            Ok(AddProviderCommand::Scaleway {
+                name: Some(name),
+                access_key: Some(cfg.access_key),
+                secret_key: Some(cfg.secret_key),
+                region: Some(cfg.region),
+                bucket: Some(bucket),
+                organization_id: Some(cfg.organization_id),
+                project_id: Some(cfg.project_id),
+                endpoint: cfg.endpoint,
+                create_bucket,
+                skip_validation,
+            })
+        }
+        AddProviderCommand::Exoscale {
+            name,
+            mut api_key,
+            mut api_secret,
+            zone,
+            bucket,
+            endpoint,
+            create_bucket,
+            skip_validation,
+        } => {
+            let name = or_prompt(name, "Provider name (your choice, e.g., my-exoscale)")?;
+            let zone = choose_region(zone, "Zone", EXOSCALE_ZONES, skip_validation)?;
+
+            println!("Create an API key under IAM > API Keys in the Exoscale console.");
+            let mut cfg = ExoscaleProviderConfig {
+                api_key: String::new(),
+                api_secret: String::new(),
+                zone,
+                bucket: String::new(),
+                endpoint,
+            };
+            if !skip_validation {
+                report_check(&mut 0, "Clock", check_clock_skew(&cfg).await);
+            }
+            loop {
+                cfg.api_key = or_prompt(api_key.take(), "API key")?;
+                cfg.api_secret = or_prompt_secret(api_secret.take(), "API secret")?;
+                let provider = ProviderConfig::Exoscale(cfg.clone());
+                if skip_validation || wizard_check_credentials(&name, &provider).await {
+                    break;
+                }
+            }
+
+            let bucket = choose_bucket(&ProviderConfig::Exoscale(cfg.clone()), bucket, create_bucket, skip_validation).await?;
+            Ok(AddProviderCommand::Exoscale {
+                name: Some(name),
+                api_key: Some(cfg.api_key),
+                api_secret: Some(cfg.api_secret),
+                zone: Some(cfg.zone),
+                bucket: Some(bucket),
+                endpoint: cfg.endpoint,
+                create_bucket,
+                skip_validation,
+            })
+        }
+    }
+}
+
+impl AddProviderCommand {
+    /// Whether every required flag was given
+    fn is_complete(&self) -> bool {
+        match self {
+            AddProviderCommand::Scaleway {
+                name,
+                access_key,
+                secret_key,
+                region,
+                bucket,
+                organization_id,
+                project_id,
+                ..
+            } => [name, access_key, secret_key, region, bucket, organization_id, project_id]
+                .iter()
+                .all(|flag| flag.is_some()),
+            AddProviderCommand::Exoscale {
+                name,
+                api_key,
+                api_secret,
+                zone,
+                bucket,
+                ..
+            } => [name, api_key, api_secret, zone, bucket].iter().all(|flag| flag.is_some()),
+        }
+    }
+}
+
+/// The given region or zone if it's known, otherwise one picked from the catalog.
+fn choose_region(given: Option<String>, kind: &str, known: &[&str], skip_validation: bool) -> Result<String> {
+    match given {
+        Some(region) if skip_validation || known.contains(&region.as_str()) => Ok(region),
+        given => {
+            if let Some(region) = given {
+                println!("Unknown {} '{}'.", kind.to_lowercase(), region);
+            }
+            Ok(known[prompt_choice(kind, known)?].to_string())
+        }
+    }
+}
+
+/// Check freshly entered credentials. Returns whether they work.
+async fn wizard_check_credentials(name: &str, provider: &ProviderConfig) -> bool {
+    if report_check(&mut 0, "IAM credentials", check_iam_credentials(name, provider).await) {
+        return true;
+    }
+    println!("Enter the credentials again.");
+    false
+}
+
+/// The given bucket, or one picked from the provider's buckets or newly created.
+async fn choose_bucket(
+    provider: &ProviderConfig,
+    given: Option<String>,
+    create_bucket: bool,
+    skip_validation: bool,
+) -> Result<String> {
+    let mut given = given;
+    loop {
+        let (bucket, create) = match given.take() {
+            Some(bucket) => (bucket, create_bucket),
+            None => {
+                // Listing needs more rights than the bucket itself, so it's only a convenience
+                let existing = list_bucket_names(provider).await.unwrap_or_default();
+                let mut options = existing.clone();
+                options.push("Create a new bucket".to_string());
+                match existing.get(prompt_choice("Bucket", &options)?) {
+                    Some(bucket) => (bucket.clone(), false),
+                    None => (prompt("New bucket name")?, true),
+                }
+            }
+        };
+        if skip_validation {
+            return Ok(bucket);
+        }
+
+        let mut candidate = provider.clone();
+        candidate.set_bucket(bucket.clone());
+        if report_check(&mut 0, "Bucket", check_or_create_bucket(&candidate, create).await) {
+            return Ok(bucket);
+        }
+        println!("Choose another bucket.");
+    }
+}
+
+// ============================================================================
+// Adoption
+// ============================================================================
+
+/// What an existing role or application may do with the objects in one folder
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct ObservedAccess {
+    read: bool,
+    write: bool,
+    delete: bool,
+}
+
+impl ObservedAccess {
+    /// The narrowest access mode covering this access, and whether it matches exactly.
+    fn access_mode(self) -> Option<(AccessMode, bool)> {
+        let mode = match (self.read, self.write, self.delete) {
+            (false, false, false) => return None,
+            (true, false, false) => AccessMode::Read,
+            (false, true, false) => AccessMode::Append,
+            (false, _, true) => AccessMode::Write,
+            (true, _, _) => AccessMode::Full,
+        };
+        let exact = mode.can_read() == self.read && mode.can_write() == self.write && mode.can_delete() == self.delete;
+        Some((mode, exact))
+    }
+
+    fn union(self, other: ObservedAccess) -> ObservedAccess {
+        ObservedAccess {
+            read: self.read || other.read,
+            write: self.write || other.write,
+            delete: self.delete || other.delete,
+        }
+    }
+}
+
+/// Inspect an existing role or application, infer the app's folder and access
+/// mode, and return its record. With `apply`, its permissions are replaced by
+/// the ones proprion generates.
+async fn adopt_app(provider: &ProviderConfig, provider_name: &str, name: &str, id: &str, apply: bool) -> Result<AppConfig> {
+    let mut warnings = Vec::new();
+    let (description, observed, key_count) = match provider {
+        ProviderConfig::Exoscale(cfg) => {
+            println!("Inspecting IAM role {}...", id);
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            let roles = client.list_roles().await.context("Failed to list IAM roles")?;
+            let role = roles
+                .iter()
+                .find(|role| role.id == id)
+                .with_context(|| format!("IAM role {} not found.", id))?;
+            let policy = role
+                .policy
+                .as_ref()
+                .context("The role has no policy to infer permissions from.")?;
+            let observed = observe_role_policy(&cfg.bucket, policy, &mut warnings)?;
+
+            let keys = client.list_api_keys().await.context("Failed to list API keys")?;
+            let key_count = keys.iter().filter(|key| key.role_id.as_deref() == Some(id)).count();
+            (role.description.clone(), observed, key_count)
+        }
+        ProviderConfig::Scaleway(cfg) => {
+            println!("Inspecting IAM application {}...", id);
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let applications = client
+                .list_applications(&cfg.organization_id, None)
+                .await
+                .context("Failed to list applications")?;
+            let application = applications
+                .iter()
+                .find(|application| application.id == id)
+                .with_context(|| format!("IAM application {} not found.", id))?;
+
+            let policy = get_scaleway_bucket_policy(cfg)?;
+            let statements: Vec<&serde_json::Value> = policy["Statement"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|statement| statement_application_id(statement) == Some(id))
+                .collect();
+            if statements.is_empty() {
+                anyhow::bail!(
+                    "Application {} has no statement in the bucket policy of '{}', so there is no folder to infer.",
+                    id,
+                    cfg.bucket
+                );
+            }
+            let observed = observe_bucket_statements(&cfg.bucket, &statements, &mut warnings);
+
+            for policy in client.list_policies(id, None).await.context("Failed to list policies")? {
+                let rules = client
+                    .list_rules(&policy.id)
+                    .await
+                    .with_context(|| format!("Failed to list rules of policy {}", policy.id))?;
+                for rule in rules {
+                    if rule.project_ids.as_ref().is_none_or(|projects| projects.is_empty()) {
+                        warnings.push(format!("IAM policy '{}' applies to the whole organization", policy.name));
+                    }
+                    for set in rule.permission_set_names.unwrap_or_default() {
+                        if !AccessMode::Full.scaleway_permission_sets().contains(&set.as_str()) {
+                            warnings.push(format!("IAM policy '{}' grants {}, which proprion never does", policy.name, set));
+                        }
+                    }
+                }
+            }
+
+            let keys = client.list_api_keys(id).await.context("Failed to list API keys")?;
+            (application.description.clone(), observed, keys.len())
+        }
+    };
+
+    if observed.is_empty() {
+        anyhow::bail!("Found no folder-scoped object permissions to infer the app's folder from.");
+    }
+
+    let combined = observed.values().fold(ObservedAccess::default(), |all, access| all.union(*access));
+    let (access, exact) = combined
+        .access_mode()
+        .context("The existing permissions don't allow any object access.")?;
+    if !exact {
+        warnings.push(format!(
+            "The existing permissions match no access mode exactly; '{}' is the narrowest that covers them",
+            access
+        ));
+    }
+    if observed.values().any(|folder| *folder != combined) {
+        warnings.push(format!("Folders have different permissions; proprion gives all of them '{}' access", access));
+    }
+
+    // Prefer the folder proprion would have created, then the first one
+    let mut prefixes: Vec<String> = Vec::new();
+    for prefix in observed.keys() {
+        match normalize_prefix(prefix) {
+            Ok(prefix) => prefixes.push(prefix),
+            Err(e) => warnings.push(format!("{:#}, ignored", e)),
+        }
+    }
+    let own = format!("apps/{}/", name);
+    let index = prefixes.iter().position(|prefix| *prefix == own).unwrap_or(0);
+    if prefixes.is_empty() {
+        anyhow::bail!("Found no usable folder to record the app with.");
+    }
+    let prefix = prefixes.remove(index);
+
+    let mut app = AppConfig::new(
+        provider_name,
+        id.to_string(),
+        description.as_deref().unwrap_or("Adopted"),
+        prefix,
+        access,
+        None,
+    );
+    app.extra_prefixes = prefixes;
+
+    println!();
+    println!("  Folder:  {}", app.prefix);
+    for extra in &app.extra_prefixes {
+        println!("           {}", extra);
+    }
+    println!("  Access:  {}", app.access);
+    for warning in &warnings {
+        println!("  Warning: {}", warning);
+    }
+    println!();
+
+    if apply {
+        println!("Replacing the existing permissions with proprion's...");
+        if let ProviderConfig::Scaleway(cfg) = provider {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let policies = client.list_policies(id, None).await.context("Failed to list policies")?;
+
+            // proprion's own policy comes first, so the app is never left without access
+            println!("  Creating IAM policy...");
+            let own = client
+                .create_policy(
+                    &format!("{}-policy", name),
+                    id,
+                    &cfg.organization_id,
+                    &cfg.project_id,
+                    &scaleway_permission_sets(&app.scopes()),
+                )
+                .await
+                .context("Failed to create policy")?;
+            for policy in policies.iter().filter(|policy| policy.id != own.id) {
+                println!("  Deleting IAM policy '{}'...", policy.name);
+                client
+                    .delete_policy(&policy.id)
+                    .await
+                    .with_context(|| format!("Failed to delete policy '{}'", policy.name))?;
+            }
+        }
+        apply_app_permissions(provider, name, &app).await?;
+    } else if !warnings.is_empty() {
+        println!("The existing permissions stay in place until proprion next changes this app's");
+        println!("permissions, or adopt it with --apply to replace them right away.");
+    }
+
+    println!("App '{}' adopted.", name);
+    if key_count > 0 {
+        println!(
+            "Its {} existing key(s) keep working but aren't recorded, as their secrets can't be read back.",
+            key_count
+        );
+        println!("Issue keys proprion manages with: proprion add-key --app {} --label <device>", name);
+    }
+
+    Ok(app)
+}
+
+/// Folder permissions granted by an Exoscale role policy on `bucket`.
+fn observe_role_policy(
+    bucket: &str,
+    policy: &exoscale::RolePolicy,
+    warnings: &mut Vec<String>,
+) -> Result<std::collections::BTreeMap<String, ObservedAccess>> {
+    if policy.default_service_strategy != "deny" {
+        warnings.push("The role allows every service its policy doesn't mention".to_string());
+    }
+    for (service, service_policy) in &policy.services {
+        if service != "sos" && service_policy.policy_type != "deny" {
+            warnings.push(format!("The role also grants access to the '{}' service", service));
+        }
+    }
+
+    let sos = policy
+        .services
+        .get("sos")
+        .context("The role's policy doesn't cover object storage.")?;
+    if sos.policy_type == "allow" {
+        anyhow::bail!("The role allows every object storage operation on every bucket, so there is no folder to infer.");
+    }
+
+    let known_operations = AccessMode::Full.exoscale_operations();
+    let mut observed = std::collections::BTreeMap::new();
+    for rule in sos.rules.iter().flatten().filter(|rule| rule.action == "allow") {
+        let expression = &rule.expression;
+        let Some(operations) = rule_operations(expression) else {
+            warnings.push(format!("A rule allows every operation: {}", expression));
+            continue;
+        };
+        match quoted_after(expression, "resources.bucket ==") {
+            Some(b) if b == bucket => {}
+            Some(b) => {
+                warnings.push(format!("A rule applies to bucket '{}': {}", b, expression));
+                continue;
+            }
+            None => {
+                warnings.push(format!("A rule applies to every bucket: {}", expression));
+                continue;
+            }
+        }
+
+        let unknown: Vec<&str> = operations
+            .iter()
+            .copied()
+            .filter(|op| !known_operations.contains(op))
+            .collect();
+        if !unknown.is_empty() {
+            warnings.push(format!("The role grants operations proprion never does: {}", unknown.join(", ")));
+        }
+
+        // Listing rules are scoped by parameters.prefix; the object rules name the folders
+        if operations.contains(&"list-objects") && quoted_after(expression, "parameters.prefix.startsWith(").is_none() {
+            warnings.push("The role can list the whole bucket".to_string());
+        }
+        let object_operations: Vec<&str> = operations.into_iter().filter(|op| *op != "list-objects").collect();
+        if object_operations.is_empty() {
+            continue;
+        }
+        let Some(prefix) = quoted_after(expression, "parameters.key.startsWith(") else {
+            warnings.push(format!("The role grants {} on the whole bucket", object_operations.join(", ")));
+            continue;
+        };
+
+        let access: &mut ObservedAccess = observed.entry(prefix.to_string()).or_default();
+        for operation in object_operations {
+            match operation {
+                "get-object" | "head-object" => access.read = true,
+                "put-object" => access.write = true,
+                "delete-object" => access.delete = true,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(observed)
+}
+
+/// Folder permissions granted by Scaleway bucket-policy statements on `bucket`.
+fn observe_bucket_statements(
+    bucket: &str,
+    statements: &[&serde_json::Value],
+    warnings: &mut Vec<String>,
+) -> std::collections::BTreeMap<String, ObservedAccess> {
+    let strings = |value: &serde_json::Value| -> Vec<String> {
+        match value {
+            serde_json::Value::String(s) => vec![s.clone()],
+            serde_json::Value::Array(items) => items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect(),
+            _ => Vec::new(),
+        }
+    };
+
+    let mut observed = std::collections::BTreeMap::new();
+    for statement in statements {
+        let sid = statement_sid(statement).unwrap_or("(no Sid)");
+        if statement.get("Effect").and_then(|e| e.as_str()) != Some("Allow") {
+            continue;
+        }
+
+        let mut access = ObservedAccess::default();
+        for action in strings(&statement["Action"]) {
+            match action.as_str() {
+                "s3:GetObject" => access.read = true,
+                "s3:PutObject" => access.write = true,
+                "s3:DeleteObject" => access.delete = true,
+                "s3:ListBucket" => {
+                    if statement.pointer("/Condition/StringLike/s3:prefix").is_none() {
+                        warnings.push(format!("Statement '{}' lets the application list the whole bucket", sid));
+                    }
+                }
+                "s3:*" | "*" => {
+                    warnings.push(format!("Statement '{}' allows every S3 action", sid));
+                    access = ObservedAccess {
+                        read: true,
+                        write: true,
+                        delete: true,
+                    };
+                }
+                other => warnings.push(format!("Statement '{}' grants {}, which proprion never does", sid, other)),
+            }
+        }
+
+        let bucket_path = format!("{}/", bucket);
+        for resource in strings(&statement["Resource"]) {
+            if resource == bucket {
+                continue;
+            }
+            let Some(path) = resource.strip_prefix(&bucket_path) else {
+                warnings.push(format!("Statement '{}' applies to {}", sid, resource));
+                continue;
+            };
+            match path.strip_suffix('*') {
+                Some("") => warnings.push(format!("Statement '{}' applies to the whole bucket", sid)),
+                Some(prefix) => {
+                    let folder: &mut ObservedAccess = observed.entry(prefix.to_string()).or_default();
+                    *folder = folder.union(access);
+                }
+                None => warnings.push(format!("Statement '{}' applies to the single object '{}'", sid, path)),
+            }
+        }
+    }
+    observed
+}
+
+/// Operations named in an Exoscale policy expression, or None if it doesn't restrict them.
+fn rule_operations(expression: &str) -> Option<Vec<&str>> {
+    if let Some(start) = expression.find("operation in [") {
+        let list = &expression[start + "operation in [".len()..];
+        let list = &list[..list.find(']')?];
+        return Some(list.split(',').map(|op| op.trim().trim_matches('\'')).collect());
+    }
+    quoted_after(expression, "operation ==").map(|op| vec![op])
+}
+
+/// The first single-quoted string after `marker` in an Exoscale policy expression.
+fn quoted_after<'a>(expression: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = &expression[expression.find(marker)? + marker.len()..];
+    let start = rest.find('\'')? + 1;
+    let end = start + rest[start..].find('\'')?;
+    Some(&rest[start..end])
+}
+
+// ============================================================================
+// Drift Detection
+// ============================================================================
+
+/// Compare the records of a provider's apps with its IAM objects and bucket
+/// policy, printing every difference. Returns the number of differences.
+///
+/// Deleted apps are left out: their IAM objects are gone by design.
+async fn audit_provider(config: &Config, provider_name: &str, provider: &ProviderConfig) -> Result<usize> {
+    println!("Auditing provider '{}' ({})...", provider_name, provider_label(provider));
+
+    let mut apps: Vec<(&String, &AppConfig)> = config
+        .apps
+        .iter()
+        .filter(|(_, app)| app.provider == provider_name && app.status != AppStatus::Deleted)
+        .collect();
+    apps.sort_by_key(|(name, _)| *name);
+
+    let mut findings: Vec<(String, Vec<String>)> = Vec::new();
+    match provider {
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            let roles = client.list_roles().await.context("Failed to list IAM roles")?;
+            let keys = client.list_api_keys().await.context("Failed to list API keys")?;
+
+            for (name, app) in &apps {
+                let mut differences = Vec::new();
+                match roles.iter().find(|role| role.id == app.id) {
+                    None => differences.push(format!("IAM role {} no longer exists", app.id)),
+                    Some(role) => {
+                        // Suspended apps keep their role with a deny-all policy
+                        let scopes = match app.status {
+                            AppStatus::Suspended => Vec::new(),
+                            _ => app.scopes(),
+                        };
+                        let expected = exoscale::sos_policy(&cfg.bucket, &exoscale_scopes(&scopes));
+                        if let Some(policy) = &role.policy {
+                            differences.extend(role_policy_drift(policy, &expected));
+                        }
+                    }
+                }
+                let role_keys = keys
+                    .iter()
+                    .filter(|key| key.role_id.as_deref() == Some(app.id.as_str()))
+                    .map(|key| key.key.as_str());
+                differences.extend(key_drift(app, role_keys));
+                findings.push((name.to_string(), differences));
+            }
+        }
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let applications = client
+                .list_applications(&cfg.organization_id, None)
+                .await
+                .context("Failed to list applications")?;
+
+            for (name, app) in &apps {
+                let mut differences = Vec::new();
+                if applications.iter().any(|a| a.id == app.id) {
+                    let permission_sets = match app.status {
+                        AppStatus::Suspended => Vec::new(),
+                        _ => scaleway_permission_sets(&app.scopes()),
+                    };
+                    // Apps left without any access keep their previous rules
+                    if app.status == AppStatus::Suspended || !permission_sets.is_empty() {
+                        differences.extend(scaleway_policy_drift(&client, cfg, &app.id, &permission_sets).await?);
+                    }
+                    let keys = client.list_api_keys(&app.id).await.context("Failed to list API keys")?;
+                    differences.extend(key_drift(app, keys.iter().map(|key| key.access_key.as_str())));
+                } else {
+                    differences.push(format!("IAM application {} no longer exists", app.id));
+                }
+                findings.push((name.to_string(), differences));
+            }
+
+            let expected: Vec<serde_json::Value> = apps
+                .iter()
+                .filter(|(_, app)| app.status != AppStatus::Suspended)
+                .flat_map(|(name, app)| scaleway_bucket_statements(cfg, name, app))
+                .collect();
+            let policy = get_scaleway_bucket_policy(cfg)?;
+            let actual = policy["Statement"].as_array().cloned().unwrap_or_default();
+            let application_ids: Vec<&str> = applications.iter().map(|a| a.id.as_str()).collect();
+            findings.push((
+                "bucket policy".to_string(),
+                bucket_policy_drift(&actual, &expected, &application_ids),
+            ));
+        }
+    }
+
+    let mut total = 0;
+    for (subject, differences) in findings {
+        if differences.is_empty() {
+            println!("  ok     {}", subject);
+            continue;
+        }
+        println!("  DRIFT  {}:", subject);
+        for difference in &differences {
+            println!("         - {}", difference);
+        }
+        total += differences.len();
+    }
+
+    Ok(total)
+}
+
+/// Differences between an Exoscale role's policy and the one proprion would generate.
+fn role_policy_drift(actual: &exoscale::RolePolicy, expected: &exoscale::RolePolicy) -> Vec<String> {
+    if actual == expected {
+        return Vec::new();
+    }
+
+    let mut differences = Vec::new();
+    if actual.default_service_strategy != expected.default_service_strategy {
+        differences.push(format!(
+            "Role policy's default strategy is '{}', proprion uses '{}'",
+            actual.default_service_strategy, expected.default_service_strategy
+        ));
+    }
+    for service in actual.services.keys().filter(|s| !expected.services.contains_key(*s)) {
+        differences.push(format!("Role policy also covers the '{}' service", service));
+    }
+
+    let rules = |policy: &exoscale::RolePolicy| {
+        policy
+            .services
+            .get("sos")
+            .and_then(|sos| sos.rules.clone())
+            .unwrap_or_default()
+    };
+    let (actual_rules, expected_rules) = (rules(actual), rules(expected));
+    for rule in actual_rules.iter().filter(|r| !expected_rules.contains(r)) {
+        differences.push(format!("Role policy has an extra rule: {} {}", rule.action, rule.expression));
+    }
+    for rule in expected_rules.iter().filter(|r| !actual_rules.contains(r)) {
+        differences.push(format!("Role policy is missing the rule: {} {}", rule.action, rule.expression));
+    }
+
+    if differences.is_empty() {
+        differences.push("Role policy differs from what proprion would generate".to_string());
+    }
+    differences
+}
+
+/// Differences between a Scaleway application's IAM policies and the single
+/// policy proprion would generate. No permission sets means no policy.
+async fn scaleway_policy_drift(
+    client: &scaleway::Client,
+    cfg: &ScalewayProviderConfig,
+    application_id: &str,
+    permission_sets: &[&str],
+) -> Result<Vec<String>> {
+    let policies = client
+        .list_policies(application_id, None)
+        .await
+        .context("Failed to list policies")?;
+
+    if permission_sets.is_empty() {
+        return Ok(policies
+            .iter()
+            .map(|policy| format!("IAM policy '{}' should have been removed while suspended", policy.name))
+            .collect());
+    }
+    if policies.is_empty() {
+        return Ok(vec!["IAM policy is missing".to_string()]);
+    }
+
+    let mut differences = Vec::new();
+    if policies.len() > 1 {
+        differences.push(format!("{} IAM policies are attached, proprion creates one", policies.len()));
+    }
+
+    let mut granted = std::collections::BTreeSet::new();
+    for policy in &policies {
+        let rules = client
+            .list_rules(&policy.id)
+            .await
+            .with_context(|| format!("Failed to list rules of policy {}", policy.id))?;
+        for rule in rules {
+            let projects = rule.project_ids.unwrap_or_default();
+            if projects != [cfg.project_id.as_str()] {
+                let scope = if projects.is_empty() {
+                    "the whole organization".to_string()
+                } else {
+                    format!("project(s) {}", projects.join(", "))
+                };
+                differences.push(format!(
+                    "IAM policy '{}' has a rule for {}, proprion only grants project {}",
+                    policy.name, scope, cfg.project_id
+                ));
+            }
+            granted.extend(rule.permission_set_names.unwrap_or_default());
+        }
+    }
+
+    let expected: std::collections::BTreeSet<String> = permission_sets.iter().map(|s| s.to_string()).collect();
+    if granted != expected {
+        differences.push(format!(
+            "IAM policy grants [{}], proprion would grant [{}]",
+            granted.into_iter().collect::<Vec<_>>().join(", "),
+            expected.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
 
-    // Step 5: Apply bucket policy
-    println!("  [5/5] Applying bucket policy for prefix '{}'...", app_prefix);
-    apply_scaleway_bucket_policy(
-        &cfg.access_key,
-        &cfg.secret_key,
-        &cfg.region,
-        &cfg.bucket,
-        &app.id,
-        name,
-        &app_prefix,
-    ).await?;
-    println!("        Bucket policy applied");
+    Ok(differences)
+}
 
-    // Output credentials
-    println!();
-    println!("=== App Created Successfully ===");
-    println!();
-    println!("S3 Credentials for '{}':", name);
-    println!();
+/// Keys that exist on the provider but aren't recorded, and recorded keys that are gone.
+fn key_drift<'a>(app: &AppConfig, on_provider: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let on_provider: Vec<&str> = on_provider.collect();
+    let recorded: Vec<(&str, &str)> = app
+        .keys
+        .iter()
+        .filter(|key| !key.revoked)
+        .flat_map(|key| {
+            std::iter::once((key.label.as_str(), key.access_key.as_str()))
+                .chain(key.retiring.as_ref().map(|r| (key.label.as_str(), r.access_key.as_str())))
+        })
+        .collect();
 
-    let creds = serde_json::json!({
-        "access_key": api_key.access_key,
-        "secret_key": api_key.secret_key,
-        "endpoint": cfg.endpoint(),
-        "region": cfg.region,
-        "bucket": cfg.bucket,
-        "prefix": app_prefix
+    let mut differences: Vec<String> = on_provider
+        .iter()
+        .filter(|access_key| !recorded.iter().any(|(_, k)| k == *access_key))
+        .map(|access_key| format!("API key {} exists but isn't recorded", access_key))
+        .collect();
+    differences.extend(
+        recorded
+            .iter()
+            .filter(|(_, access_key)| !on_provider.contains(access_key))
+            .map(|(label, access_key)| format!("Recorded key '{}' ({}) no longer exists", label, access_key)),
+    );
+    differences
+}
+
+/// Differences between proprion's statements in a bucket policy and the ones it would generate.
+fn bucket_policy_drift(
+    actual: &[serde_json::Value],
+    expected: &[serde_json::Value],
+    application_ids: &[&str],
+) -> Vec<String> {
+    let mut differences = Vec::new();
+    for statement in actual {
+        let Some(sid) = statement_sid(statement).filter(|sid| sid.starts_with("proprion-")) else {
+            continue;
+        };
+        if let Some(id) = statement_application_id(statement).filter(|id| !application_ids.contains(id)) {
+            differences.push(format!("Statement '{}' grants application {}, which no longer exists", sid, id));
+            continue;
+        }
+        match expected.iter().find(|e| statement_sid(e) == Some(sid)) {
+            None => differences.push(format!("Statement '{}' doesn't belong to any recorded app", sid)),
+            Some(e) if e != statement => {
+                differences.push(format!("Statement '{}' differs from what proprion would generate", sid))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for statement in expected {
+        let sid = statement_sid(statement).unwrap_or_default();
+        if !actual.iter().any(|a| statement_sid(a) == Some(sid)) {
+            differences.push(format!("Statement '{}' is missing", sid));
+        }
+    }
+    differences
+}
+
+// ============================================================================
+// Emergency Revocation
+// ============================================================================
+
+/// Revoke every proprion-managed key, best effort, returning one report entry per key.
+///
+/// Registered keys are marked revoked so `reissue-keys` can replace them. Keys the
+/// registry doesn't know about are found on the provider: Exoscale keys bound to
+/// `proprion-*` roles and any key of a registered or proprion-tagged Scaleway application.
+async fn revoke_all_keys(config: &mut Config, provider_filter: Option<&str>) -> Vec<serde_json::Value> {
+    let mut entries = Vec::new();
+
+    let mut provider_names: Vec<String> = config
+        .providers
+        .keys()
+        .filter(|name| provider_filter.is_none_or(|f| f == name.as_str()))
+        .cloned()
+        .collect();
+    provider_names.sort();
+
+    for provider_name in provider_names {
+        let provider = config.providers[&provider_name].clone();
+        println!("Revoking keys on '{}'...", provider_name);
+
+        let mut app_names: Vec<String> = config
+            .apps
+            .iter()
+            .filter(|(_, app)| app.provider == provider_name)
+            .map(|(name, _)| name.clone())
+            .collect();
+        app_names.sort();
+
+        let mut handled: Vec<String> = Vec::new();
+        for app_name in &app_names {
+            let app = config.apps.get_mut(app_name).expect("app listed above");
+            for key in &mut app.keys {
+                // Keys that failed to revoke stay live, so they keep their record
+                if !key.revoked {
+                    let result = revoke_app_key(&provider, &key.access_key).await;
+                    entries.push(revocation_entry(&provider_name, Some(app_name), Some(&key.label), &key.access_key, &result));
+                    handled.push(key.access_key.clone());
+                    key.revoked = result.is_ok();
+                }
+                if let Some(retiring) = key.retiring.take() {
+                    let result = revoke_app_key(&provider, &retiring.access_key).await;
+                    entries.push(revocation_entry(&provider_name, Some(app_name), Some(&key.label), &retiring.access_key, &result));
+                    handled.push(retiring.access_key.clone());
+                    if result.is_err() {
+                        key.retiring = Some(retiring);
+                    }
+                }
+            }
+        }
+
+        // Sweep keys that exist on the provider but not in the registry
+        let unknown = match find_unregistered_keys(&provider, config, &provider_name, &handled).await {
+            Ok(keys) => keys,
+            Err(e) => {
+                println!("  Warning: could not list keys on the provider: {:#}", e);
+                entries.push(serde_json::json!({
+                    "provider": provider_name,
+                    "error": format!("Failed to list provider keys: {:#}", e),
+                }));
+                Vec::new()
+            }
+        };
+        for (owner, access_key) in unknown {
+            let result = revoke_app_key(&provider, &access_key).await;
+            entries.push(revocation_entry(&provider_name, Some(&owner), None, &access_key, &result));
+        }
+    }
+
+    entries
+}
+
+/// Keys on the provider that belong to proprion apps but were not revoked from
+/// the registry, as (owner, access key) pairs.
+async fn find_unregistered_keys(
+    provider: &ProviderConfig,
+    config: &Config,
+    provider_name: &str,
+    handled: &[String],
+) -> Result<Vec<(String, String)>> {
+    let mut found = Vec::new();
+
+    match provider {
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let mut owners: Vec<(String, String)> = config
+                .apps
+                .iter()
+                .filter(|(_, a)| a.provider == provider_name && a.status != AppStatus::Deleted)
+                .map(|(name, app)| (name.clone(), app.id.clone()))
+                .collect();
+            // Applications proprion created but lost track of
+            for application in client.list_applications(&cfg.organization_id, Some(scaleway::TAG)).await? {
+                if !owners.iter().any(|(_, id)| *id == application.id) {
+                    owners.push((application.name, application.id));
+                }
+            }
+            for (name, application_id) in owners {
+                for key in client.list_api_keys(&application_id).await? {
+                    if !handled.contains(&key.access_key) {
+                        found.push((name.clone(), key.access_key));
+                    }
+                }
+            }
+        }
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            let roles = client.list_roles().await?;
+            for key in client.list_api_keys().await? {
+                let Some(role) = roles.iter().find(|r| key.role_id.as_deref() == Some(r.id.as_str())) else {
+                    continue;
+                };
+                let Some(owner) = role.name.as_deref().and_then(|n| n.strip_prefix("proprion-")) else {
+                    continue;
+                };
+                if !handled.contains(&key.key) {
+                    found.push((owner.to_string(), key.key));
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+fn revocation_entry(
+    provider: &str,
+    app: Option<&str>,
+    label: Option<&str>,
+    access_key: &str,
+    result: &Result<()>,
+) -> serde_json::Value {
+    match result {
+        Ok(()) => println!("  Revoked {} ({})", access_key, app.unwrap_or("unknown app")),
+        Err(e) => println!("  FAILED {} ({}): {:#}", access_key, app.unwrap_or("unknown app"), e),
+    }
+    serde_json::json!({
+        "provider": provider,
+        "app": app,
+        "label": label,
+        "access_key": access_key,
+        "revoked_at": format_rfc3339(now_unix()).ok(),
+        "error": result.as_ref().err().map(|e| format!("{:#}", e)),
+    })
+}
+
+fn write_revocation_report(path: &std::path::Path, entries: &[serde_json::Value]) -> Result<()> {
+    let report = serde_json::json!({
+        "generated_at": format_rfc3339(now_unix())?,
+        "keys": entries,
     });
+    std::fs::write(path, serde_json::to_string_pretty(&report)?)
+        .with_context(|| format!("Failed to write report: {}", path.display()))
+}
 
-    println!("{}", serde_json::to_string_pretty(&creds)?);
-    println!();
-    println!("IMPORTANT: Save the secret_key now - it cannot be retrieved later!");
-    println!();
-    println!("Application ID: {} (save this to delete the app later)", app.id);
-    println!();
-    println!("This app can ONLY access: s3://{}/{}/", cfg.bucket, app_prefix);
+/// Replace every key marked revoked with a fresh key carrying the same label.
+/// Keys issued before a failure stay recorded in `config`.
+async fn reissue_revoked_keys(config: &mut Config, provider_filter: Option<&str>, app_filter: Option<&str>) -> Result<()> {
+    // Exoscale roles made by early versions of proprion can't be edited; they are
+    // recreated here, which needs new keys too
+    let mut legacy_roles = std::collections::HashSet::new();
+    for (provider_name, provider) in &config.providers {
+        if let ProviderConfig::Exoscale(cfg) = provider {
+            if provider_filter.is_none_or(|p| p == provider_name) {
+                let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+                let roles = client.list_roles().await.context("Failed to list IAM roles")?;
+                legacy_roles.extend(roles.into_iter().filter(|r| r.editable == Some(false)).map(|r| r.id));
+            }
+        }
+    }
 
-    Ok(())
+    let mut app_names: Vec<String> = config
+        .apps
+        .iter()
+        .filter(|(name, app)| {
+            provider_filter.is_none_or(|p| p == app.provider)
+                && app_filter.is_none_or(|a| a == name.as_str())
+                && app.status != AppStatus::Deleted
+                && (app.keys.iter().any(|k| k.revoked) || legacy_roles.contains(&app.id))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    app_names.sort();
+
+    if app_names.is_empty() {
+        println!("No revoked keys to reissue.");
+        return Ok(());
+    }
+
+    let mut failure = None;
+    for name in app_names {
+        let mut app = config.apps[&name].clone();
+        let Some(provider) = config.get_provider(&app.provider) else {
+            println!("  Skipping '{}': provider '{}' not found", name, app.provider);
+            continue;
+        };
+
+        if let ProviderConfig::Exoscale(cfg) = provider {
+            if legacy_roles.contains(&app.id) {
+                if let Err(err) = recreate_editable_role(provider, cfg, &name, &mut app).await {
+                    println!("  Failed: {:#}", err);
+                    failure.get_or_insert(err);
+                }
+            }
+        }
+
+        let labels: Vec<String> = app.keys.iter().filter(|k| k.revoked).map(|k| k.label.clone()).collect();
+        for label in labels {
+            println!("Reissuing key '{}' of app '{}'...", label, name);
+            match issue_app_key(provider, &name, &label, &app).await {
+                Ok(key) => {
+                    let printed = print_key_credentials(provider, &name, &app, &key);
+                    *app.get_key_mut(&label).expect("label listed above") = key;
+                    if let Err(err) = printed {
+                        failure.get_or_insert(err);
+                    }
+                }
+                Err(err) => {
+                    println!("  Failed: {:#}", err);
+                    failure.get_or_insert(err);
+                }
+            }
+        }
+
+        config.set_app(name, app);
+    }
+
+    match failure {
+        Some(err) => Err(err.context("Some keys could not be reissued")),
+        None => Ok(()),
+    }
 }
 
-async fn list_scaleway_apps(cfg: &ScalewayProviderConfig) -> Result<()> {
-    let client = scaleway::Client::new(cfg.secret_key.clone());
+/// Revoke keys that are past their expiry or rotation grace period.
+/// With `dry_run`, only print what would be revoked. Keys that could not be
+/// revoked keep their record, so the next run retries them.
+async fn collect_garbage(config: &mut Config, dry_run: bool) -> Result<()> {
+    let now = now_unix();
+    let mut names: Vec<String> = config.apps.keys().cloned().collect();
+    names.sort();
 
-    println!("Fetching applications...");
-    let apps = client
-        .list_applications(&cfg.organization_id)
-        .await
-        .context("Failed to list applications")?;
+    let mut revoked = 0;
+    let mut failure = None;
+    for name in names {
+        let mut app = config.apps[&name].clone();
+        let Some(provider) = config.get_provider(&app.provider) else {
+            println!("  Skipping '{}': provider '{}' not found", name, app.provider);
+            continue;
+        };
 
-    if apps.is_empty() {
-        println!("No applications found.");
-    } else {
-        println!();
-        println!("Applications:");
-        for app in apps {
-            println!("  - {} (ID: {})", app.name, app.id);
-            if let Some(desc) = &app.description {
-                if !desc.is_empty() {
-                    println!("    {}", desc);
+        // Suspended apps expire too, so resuming can't bring their keys back
+        let live = matches!(app.status, AppStatus::Active | AppStatus::Suspended);
+        if live && app.expires_in(now) == Some(0) {
+            if dry_run {
+                println!("  Would revoke the expired keys of '{}'", name);
+                continue;
+            }
+            let mut kept = Vec::new();
+            for mut key in std::mem::take(&mut app.keys).into_iter().filter(|key| !key.revoked) {
+                let mut access_keys = vec![key.access_key.clone()];
+                access_keys.extend(key.retiring.as_ref().map(|r| r.access_key.clone()));
+                let mut failed = Vec::new();
+                for access_key in access_keys {
+                    println!("  Revoking expired key {} of '{}'...", access_key, name);
+                    // Scaleway already rejects expired keys, deleting them just tidies up
+                    match revoke_app_key(provider, &access_key).await {
+                        Ok(()) => revoked += 1,
+                        Err(e) if matches!(provider, ProviderConfig::Scaleway(_)) => {
+                            println!("    Warning: {:#}", e);
+                        }
+                        Err(e) => {
+                            println!("    Failed: {:#}", e);
+                            failure.get_or_insert(e);
+                            failed.push(access_key);
+                        }
+                    }
+                }
+                if !failed.is_empty() {
+                    key.revoked = !failed.contains(&key.access_key);
+                    key.retiring = key.retiring.filter(|r| failed.contains(&r.access_key));
+                    kept.push(key);
+                }
+            }
+            if kept.is_empty() {
+                app.status = AppStatus::Expired;
+            }
+            app.keys = kept;
+        }
+
+        for key in &mut app.keys {
+            let Some(retiring) = &key.retiring else { continue };
+            if retiring.revoke_after.is_some_and(|at| at <= now) {
+                if dry_run {
+                    println!("  Would revoke rotated-out key {} of '{}'", retiring.access_key, name);
+                    continue;
+                }
+                println!("  Revoking rotated-out key {} of '{}'...", retiring.access_key, name);
+                match revoke_app_key(provider, &retiring.access_key).await {
+                    Ok(()) => {
+                        key.retiring = None;
+                        revoked += 1;
+                    }
+                    Err(e) => {
+                        println!("    Failed: {:#}", e);
+                        failure.get_or_insert(e);
+                    }
                 }
             }
         }
+
+        config.set_app(name, app);
     }
 
-    Ok(())
+    if dry_run {
+        return Ok(());
+    }
+    if revoked == 0 && failure.is_none() {
+        println!("Nothing to revoke.");
+    } else {
+        println!("Revoked {} key(s).", revoked);
+    }
+
+    match failure {
+        Some(err) => Err(err.context("Some keys could not be revoked")),
+        None => Ok(()),
+    }
 }
 
-async fn delete_scaleway_app(cfg: &ScalewayProviderConfig, app_id: &str) -> Result<()> {
-    let client = scaleway::Client::new(cfg.secret_key.clone());
+/// An IAM object or bucket-policy statement that follows proprion's naming but
+/// no app record accounts for
+enum Orphan {
+    /// `proprion-*` role without keys
+    ExoscaleRole { id: String, name: String },
+    /// `proprion-*` key whose role no longer exists
+    ExoscaleKey { key: String, name: String },
+    /// Application left without a policy
+    ScalewayApplication { id: String, name: String },
+    /// `proprion-*` statement not generated for any current app
+    BucketStatement { sid: String },
+}
 
-    println!("Deleting application {}...", app_id);
+impl std::fmt::Display for Orphan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Orphan::ExoscaleRole { id, name } => write!(f, "IAM role {} ({}): no keys and no app record", name, id),
+            Orphan::ExoscaleKey { key, name } => write!(f, "API key {} ({}): its role no longer exists", name, key),
+            Orphan::ScalewayApplication { id, name } => {
+                write!(f, "IAM application {} ({}): no policy and no app record", name, id)
+            }
+            Orphan::BucketStatement { sid } => write!(f, "bucket-policy statement '{}': no current app uses it", sid),
+        }
+    }
+}
 
-    // Just delete the application directly
-    // Scaleway should cascade delete associated resources
-    client
-        .delete_application(app_id)
-        .await
-        .context("Failed to delete application")?;
+/// Find and, after confirmation, delete what failed runs left behind on every provider.
+async fn collect_orphans(config: &Config, dry_run: bool, yes: bool) -> Result<()> {
+    let mut names: Vec<&String> = config.list_providers();
+    names.sort();
 
-    println!("Application deleted successfully.");
-    println!();
-    println!("Note: You may want to manually update the bucket policy to remove this app's statement.");
+    let mut plan = Vec::new();
+    for name in names {
+        match find_orphans(config, name, &config.providers[name]).await {
+            Ok(orphans) if !orphans.is_empty() => plan.push((name, orphans)),
+            Ok(_) => {}
+            Err(e) => println!("  Skipping provider '{}': {:#}", name, e),
+        }
+    }
+
+    if plan.is_empty() {
+        println!("No leftovers to delete.");
+        return Ok(());
+    }
+
+    let total: usize = plan.iter().map(|(_, orphans)| orphans.len()).sum();
+    println!("Leftovers from failed or interrupted runs:");
+    for (name, orphans) in &plan {
+        println!("  {}:", name);
+        for orphan in orphans {
+            println!("    - {}", orphan);
+        }
+    }
+
+    if dry_run {
+        println!("Dry run: {} object(s) would be deleted.", total);
+        return Ok(());
+    }
+    if !yes && !confirm(&format!("Delete these {} object(s)?", total))? {
+        println!("Nothing deleted.");
+        return Ok(());
+    }
+
+    let mut deleted = 0;
+    for (name, orphans) in plan {
+        deleted += delete_orphans(&config.providers[name], orphans).await?;
+    }
+    println!("Deleted {} object(s).", deleted);
 
     Ok(())
 }
 
+/// Everything on a provider that matches proprion's naming but isn't accounted for.
+///
+/// Objects referenced by a record that isn't deleted are never orphans.
+async fn find_orphans(config: &Config, provider_name: &str, provider: &ProviderConfig) -> Result<Vec<Orphan>> {
+    let records: Vec<(&String, &AppConfig)> = config
+        .apps
+        .iter()
+        .filter(|(_, app)| app.provider == provider_name)
+        .collect();
+    let in_use = |id: &str| records.iter().any(|(_, app)| app.status != AppStatus::Deleted && app.id == id);
+
+    let mut orphans = Vec::new();
+    match provider {
+        ProviderConfig::Exoscale(cfg) => {
+            let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+            let roles = client.list_roles().await.context("Failed to list IAM roles")?;
+            let keys = client.list_api_keys().await.context("Failed to list API keys")?;
+
+            for role in &roles {
+                let Some(name) = role.name.as_deref().filter(|n| n.starts_with("proprion-")) else {
+                    continue;
+                };
+                let has_keys = keys.iter().any(|key| key.role_id.as_deref() == Some(role.id.as_str()));
+                if !has_keys && !in_use(&role.id) {
+                    orphans.push(Orphan::ExoscaleRole {
+                        id: role.id.clone(),
+                        name: name.to_string(),
+                    });
+                }
+            }
+            // Keys without a role are unrestricted keys made by hand, never proprion's
+            for key in keys.iter().filter(|key| key.name.starts_with("proprion-")) {
+                let Some(role_id) = key.role_id.as_deref() else {
+                    continue;
+                };
+                if !roles.iter().any(|role| role.id == role_id) {
+                    orphans.push(Orphan::ExoscaleKey {
+                        key: key.key.clone(),
+                        name: key.name.clone(),
+                    });
+                }
+            }
+        }
+        ProviderConfig::Scaleway(cfg) => {
+            let client = scaleway::Client::new(cfg.secret_key.clone());
+            let applications = client
+                .list_applications(&cfg.organization_id, None)
+                .await
+                .context("Failed to list applications")?;
+
+            // Sharing a record's name isn't enough: an application without a policy may
+            // still get its permissions from an IAM group
+            for application in &applications {
+                let created_by_proprion = application.is_tagged() || application.name.starts_with("proprion-");
+                if !created_by_proprion || in_use(&application.id) {
+                    continue;
+                }
+                let policies = client
+                    .list_policies(&application.id, None)
+                    .await
+                    .context("Failed to list policies")?;
+                if policies.is_empty() {
+                    orphans.push(Orphan::ScalewayApplication {
+                        id: application.id.clone(),
+                        name: application.name.clone(),
+                    });
+                }
+            }
+
+            let expected: Vec<serde_json::Value> = records
+                .iter()
+                .filter(|(_, app)| matches!(app.status, AppStatus::Active | AppStatus::Expired))
+                .flat_map(|(name, app)| scaleway_bucket_statements(cfg, name, app))
+                .collect();
+            let policy = get_scaleway_bucket_policy(cfg)?;
+            for statement in policy["Statement"].as_array().into_iter().flatten() {
+                let Some(sid) = statement_sid(statement).filter(|sid| sid.starts_with("proprion-")) else {
+                    continue;
+                };
+                if !expected.iter().any(|e| statement_sid(e) == Some(sid)) {
+                    orphans.push(Orphan::BucketStatement { sid: sid.to_string() });
+                }
+            }
+        }
+    }
+
+    Ok(orphans)
+}
+
+/// Delete orphans found on a provider. Returns how many were deleted.
+async fn delete_orphans(provider: &ProviderConfig, orphans: Vec<Orphan>) -> Result<usize> {
+    let mut deleted = 0;
+    let mut stale_sids = Vec::new();
+    for orphan in orphans {
+        println!("  Deleting {}...", orphan);
+        match (&orphan, provider) {
+            (Orphan::ExoscaleRole { id, .. }, ProviderConfig::Exoscale(cfg)) => {
+                let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+                client.delete_role(id).await.context("Failed to delete IAM role")?;
+            }
+            (Orphan::ExoscaleKey { key, .. }, ProviderConfig::Exoscale(cfg)) => {
+                let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+                client.delete_api_key(key).await.context("Failed to delete API key")?;
+            }
+            (Orphan::ScalewayApplication { id, .. }, ProviderConfig::Scaleway(cfg)) => {
+                let client = scaleway::Client::new(cfg.secret_key.clone());
+                client.delete_application(id).await.context("Failed to delete application")?;
+            }
+            (Orphan::BucketStatement { sid }, ProviderConfig::Scaleway(_)) => {
+                // Removed together below, in a single bucket-policy update
+                stale_sids.push(sid.clone());
+                continue;
+            }
+            _ => unreachable!("orphans are only found on their own provider type"),
+        }
+        deleted += 1;
+    }
+
+    if let ProviderConfig::Scaleway(cfg) = provider {
+        if !stale_sids.is_empty() {
+            edit_scaleway_bucket_policy(cfg, |statements| {
+                statements.retain(|s| !statement_sid(s).is_some_and(|sid| stale_sids.iter().any(|stale| stale == sid)));
+            })
+            .await?;
+            deleted += stale_sids.len();
+        }
+    }
+
+    Ok(deleted)
+}
+
 // ============================================================================
-// Exoscale Implementation
+// Helper Functions
 // ============================================================================
 
-async fn create_exoscale_app(cfg: &ExoscaleProviderConfig, name: &str, description: &str) -> Result<()> {
-    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
-    let app_prefix = format!("apps/{}/", name);
+/// Print the locally recorded state of an app under its list-apps entry.
+fn print_app_record(config: &Config, name: &str, app: &AppConfig, show_keys: bool) {
+    println!("    Access: {}", app.access);
+    if !app.extra_prefixes.is_empty() {
+        println!("    Also: {}", app.extra_prefixes.join(", "));
+    }
+    for grant in &app.grants {
+        println!("    Can {} folder of '{}' ({})", grant.access, grant.from, grant.prefix);
+    }
+    let mut shared_with: Vec<String> = config
+        .apps
+        .iter()
+        .flat_map(|(other, o)| {
+            o.grants
+                .iter()
+                .filter(|g| g.from == name)
+                .map(move |g| format!("{} ({})", other, g.access))
+        })
+        .collect();
+    if !shared_with.is_empty() {
+        shared_with.sort();
+        println!("    Shared with: {}", shared_with.join(", "));
+    }
+    if let Some(quota) = app.quota {
+        println!("    Quota: {}", format_bytes(quota));
+    }
+    if app.over_quota {
+        println!("    OVER QUOTA - read-only until usage drops (see 'proprion enforce-quotas')");
+    }
+    match app.status {
+        AppStatus::Active => {}
+        AppStatus::Suspended => println!("    SUSPENDED - all access paused"),
+        AppStatus::Expired => {
+            println!("    Keys expired and revoked");
+            return;
+        }
+        AppStatus::Deleted => {
+            println!("    DELETED - keys revoked, restorable with 'proprion restore-app {}'", name);
+            return;
+        }
+    }
+    match app.expires_in(now_unix()) {
+        Some(0) => println!("    Keys expired (run 'proprion gc' to revoke them)"),
+        Some(secs) => println!("    Keys expire in {}", format_duration(secs)),
+        None => {}
+    }
+    if show_keys {
+        println!("    Keys:");
+        for key in &app.keys {
+            let created = match key.created_at {
+                Some(ts) => format_date(ts),
+                None => "unknown".to_string(),
+            };
+            let revoked = if key.revoked { ", REVOKED" } else { "" };
+            println!("      - {}: {} (created {}{})", key.label, key.access_key, created, revoked);
+            if let Some(retiring) = &key.retiring {
+                println!("        rotating out: {}", retiring.access_key);
+            }
+        }
+    }
+}
 
-    println!("Creating app '{}' on Exoscale...", name);
+/// List deleted apps of a provider that can still be restored.
+fn print_deleted_apps(config: &Config, provider: &str) {
+    let mut deleted: Vec<_> = config
+        .apps
+        .iter()
+        .filter(|(_, app)| app.provider == provider && app.status == AppStatus::Deleted)
+        .collect();
+    if deleted.is_empty() {
+        return;
+    }
+    deleted.sort_by_key(|(name, _)| name.as_str());
 
-    // Step 1: Create bucket if needed
-    println!("  [1/3] Checking/creating bucket '{}'...", cfg.bucket);
-    ensure_bucket_exists(&cfg.api_key, &cfg.api_secret, &cfg.zone, &cfg.bucket, "exoscale").await?;
-    println!("        Bucket ready");
+    let now = now_unix();
+    println!();
+    println!("Deleted apps (restore with 'proprion restore-app <name>'):");
+    for (name, app) in deleted {
+        match app.purge_after {
+            Some(at) if at > now => println!("  - {} ({}, purged in {})", name, app.prefix, format_duration(at - now)),
+            _ => println!("  - {} ({}, recovery window ended - run 'proprion purge')", name, app.prefix),
+        }
+    }
+}
 
-    // Step 2: Create IAM role with scoped policy
-    println!("  [2/3] Creating IAM role with scoped policy...");
-    let role_name = format!("proprion-{}", name);
-    let role = client
-        .create_role(&role_name, description, &cfg.bucket, &app_prefix)
-        .await
-        .context("Failed to create IAM role")?;
-    println!("        Role ID: {}", role.id);
+/// Validate a bucket prefix and make sure it ends with a slash.
+fn normalize_prefix(prefix: &str) -> Result<String> {
+    let prefix = prefix.trim();
+    if prefix.is_empty() || prefix.starts_with('/') || prefix.contains('\'') {
+        anyhow::bail!("Invalid prefix '{}'", prefix);
+    }
+    if prefix.ends_with('/') {
+        Ok(prefix.to_string())
+    } else {
+        Ok(format!("{}/", prefix))
+    }
+}
 
-    // Wait for role to propagate (Exoscale async operations need time)
-    println!("        Waiting for role to propagate...");
-    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+/// Format a Unix timestamp as a calendar date, e.g. 2025-01-31.
+fn format_date(timestamp: u64) -> String {
+    format_rfc3339(timestamp)
+        .map(|s| s[..10].to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Format a Unix timestamp as an RFC 3339 date, e.g. 2025-01-31T12:00:00Z.
+fn format_rfc3339(timestamp: u64) -> Result<String> {
+    let datetime = time::OffsetDateTime::from_unix_timestamp(timestamp as i64)
+        .context("Timestamp out of range")?;
+    datetime
+        .format(&time::format_description::well_known::Rfc3339)
+        .context("Failed to format timestamp")
+}
+
+/// Format a byte count with binary units, e.g. 1.5 GiB.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Current time as a Unix timestamp in seconds.
+fn now_unix() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
 
-    // Step 3: Create API key attached to role
-    println!("  [3/3] Creating API key...");
-    let key_name = format!("proprion-{}-key", name);
-    let api_key = client
-        .create_api_key(&key_name, &role.id)
-        .await
-        .context("Failed to create API key")?;
-    let access_key = &api_key.key;
-    let secret_key = api_key.secret
-        .as_ref()
-        .context("API key response missing secret")?;
-    println!("        Access Key: {}", access_key);
+/// Parse a duration like "90s", "30m", "24h", "7d" or "2w" into seconds.
+fn parse_duration(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .with_context(|| format!("Missing unit in duration '{}' (use s, m, h, d or w)", input))?;
+    let (value, unit) = input.split_at(split);
+    let value: u64 = value
+        .parse()
+        .with_context(|| format!("Invalid duration '{}'", input))?;
+    let multiplier: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => anyhow::bail!("Unknown unit '{}' in duration '{}' (use s, m, h, d or w)", unit, input),
+    };
+    // Capped so that adding it to the current time can't overflow either
+    value
+        .checked_mul(multiplier)
+        .filter(|secs| *secs <= 100 * 365 * 24 * 60 * 60)
+        .with_context(|| format!("Duration '{}' is too long (at most 100 years)", input))
+}
 
-    // Output credentials
-    println!();
-    println!("=== App Created Successfully ===");
-    println!();
-    println!("S3 Credentials for '{}':", name);
-    println!();
+/// Parse a size like "500MB", "10GiB" or "1.5TB" into bytes.
+/// KB/MB/GB/TB are powers of 1000, KiB/MiB/GiB/TiB powers of 1024.
+fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+    let value: f64 = value
+        .parse()
+        .with_context(|| format!("Invalid size '{}'", input))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000_u64.pow(2),
+        "gb" => 1000_u64.pow(3),
+        "tb" => 1000_u64.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => anyhow::bail!("Unknown unit '{}' in size '{}' (use e.g. MB, GB, MiB or GiB)", unit, input),
+    };
+    Ok((value * multiplier as f64) as u64)
+}
 
-    let creds = serde_json::json!({
-        "access_key": access_key,
-        "secret_key": secret_key,
-        "endpoint": cfg.endpoint(),
-        "zone": cfg.zone,
-        "bucket": cfg.bucket,
-        "prefix": app_prefix
-    });
+/// Format a number of seconds as a short human-readable duration.
+fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes.max(1))
+    }
+}
 
-    println!("{}", serde_json::to_string_pretty(&creds)?);
-    println!();
-    println!("IMPORTANT: Save the secret_key now - it cannot be retrieved later!");
-    println!();
-    println!("Role ID: {} (save this to delete the app later)", role.id);
-    println!();
-    println!("This app can ONLY access: s3://{}/{}", cfg.bucket, app_prefix);
+/// Fail with a hint to use flags when there's no terminal to ask on.
+fn require_terminal(label: &str) -> Result<()> {
+    use std::io::IsTerminal;
 
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "No terminal to ask for '{}' on. Pass it on the command line instead (see --help).",
+            label
+        );
+    }
     Ok(())
 }
 
-async fn list_exoscale_apps(cfg: &ExoscaleProviderConfig) -> Result<()> {
-    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+/// Ask for a value on the terminal until a non-empty one is given.
+fn prompt(label: &str) -> Result<String> {
+    use std::io::Write;
 
-    println!("Fetching IAM roles...");
-    let roles = client
-        .list_roles()
-        .await
-        .context("Failed to list roles")?;
+    require_terminal(label)?;
+    loop {
+        print!("{}: ", label);
+        std::io::stdout().flush()?;
 
-    // Filter to only show roles created by Proprion (have "proprion-" prefix)
-    let proprion_roles: Vec<_> = roles
-        .iter()
-        .filter(|r| {
-            r.name
-                .as_ref()
-                .map(|n| n.starts_with("proprion-"))
-                .unwrap_or(false)
-        })
-        .collect();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            anyhow::bail!("No value given for '{}'.", label);
+        }
+        let answer = answer.trim();
+        if !answer.is_empty() {
+            return Ok(answer.to_string());
+        }
+    }
+}
 
-    if proprion_roles.is_empty() {
-        println!("No Proprion apps found.");
-    } else {
-        println!();
-        println!("Proprion Apps (Exoscale IAM roles):");
-        for role in proprion_roles {
-            let name = role.name.as_deref().unwrap_or("unknown");
-            let app_name = name.strip_prefix("proprion-").unwrap_or(name);
-            let desc = role.description.as_deref().unwrap_or("");
-            println!("  - {} (Role ID: {})", app_name, role.id);
-            if !desc.is_empty() {
-                println!("    {}", desc);
-            }
+/// Like [`prompt`], without echoing what is typed or pasted.
+fn prompt_secret(label: &str) -> Result<String> {
+    require_terminal(label)?;
+    loop {
+        let answer = rpassword::prompt_password(format!("{} (hidden): ", label))?;
+        let answer = answer.trim();
+        if !answer.is_empty() {
+            return Ok(answer.to_string());
         }
     }
+}
 
+/// Check that a name can be embedded in IAM object names and bucket-policy Sids.
+fn validate_name(kind: &str, value: &str) -> Result<()> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!("Invalid {} '{}': use only letters, digits, '-' and '_'.", kind, value);
+    }
+    if value.contains("-from-") {
+        anyhow::bail!("Invalid {} '{}': '-from-' is reserved for shared folders.", kind, value);
+    }
+    if value.ends_with("-list") {
+        anyhow::bail!("Invalid {} '{}': names can't end in '-list'.", kind, value);
+    }
     Ok(())
 }
 
-async fn delete_exoscale_app(cfg: &ExoscaleProviderConfig, role_id: &str) -> Result<()> {
-    let client = exoscale::Client::new(cfg.api_key.clone(), cfg.api_secret.clone(), &cfg.zone);
+/// The given value, or one asked for on the terminal.
+fn or_prompt(given: Option<String>, label: &str) -> Result<String> {
+    given.map_or_else(|| prompt(label), Ok)
+}
 
-    println!("Deleting IAM role {}...", role_id);
+/// The given secret, or one asked for on the terminal without echo.
+fn or_prompt_secret(given: Option<String>, label: &str) -> Result<String> {
+    given.map_or_else(|| prompt_secret(label), Ok)
+}
 
-    // First, list and delete API keys associated with this role
-    let api_keys = client
-        .list_api_keys()
-        .await
-        .context("Failed to list API keys")?;
+/// Ask to pick one of `options` by number. Returns its index.
+fn prompt_choice(label: &str, options: &[impl AsRef<str>]) -> Result<usize> {
+    if let [only] = options {
+        println!("{}: {}", label, only.as_ref());
+        return Ok(0);
+    }
 
-    for key in api_keys {
-        if key.role_id.as_deref() == Some(role_id) {
-            println!("  Deleting API key {}...", key.key);
-            client.delete_api_key(&key.key).await.ok();
+    require_terminal(label)?;
+    println!("{}:", label);
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option.as_ref());
+    }
+    loop {
+        let answer = prompt(&format!("Choose 1-{}", options.len()))?;
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+            _ => println!("Enter a number between 1 and {}.", options.len()),
         }
     }
+}
 
-    // Then delete the role
-    client
-        .delete_role(role_id)
-        .await
-        .context("Failed to delete role")?;
+/// Ask a yes/no question on the terminal, defaulting to no.
+fn confirm(prompt: &str) -> Result<bool> {
+    use std::io::Write;
 
-    println!("Role and associated API keys deleted successfully.");
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush()?;
 
-    Ok(())
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
-// ============================================================================
-// Helper Functions
-// ============================================================================
-
 async fn ensure_bucket_exists(
     access_key: &str,
     secret_key: &str,
     region: &str,
+    endpoint: &str,
     bucket_name: &str,
-    provider: &str,
 ) -> Result<()> {
-    let endpoint = match provider {
-        "scaleway" => format!("https://s3.{}.scw.cloud", region),
-        "exoscale" => format!("https://sos-{}.exo.io", region),
-        _ => anyhow::bail!("Unknown provider: {}", provider),
-    };
-
     let s3_region = Region::Custom {
         region: region.to_string(),
-        endpoint: endpoint.clone(),
+        endpoint: endpoint.to_string(),
     };
 
     let credentials = Credentials::new(Some(access_key), Some(secret_key), None, None, None)
@@ -591,62 +5975,271 @@ async fn ensure_bucket_exists(
     }
 }
 
-async fn apply_scaleway_bucket_policy(
-    access_key: &str,
-    secret_key: &str,
-    region: &str,
-    bucket: &str,
+/// The provider's bucket, accessed with its root credentials.
+fn provider_bucket(provider: &ProviderConfig) -> Result<Box<Bucket>> {
+    let (access_key, secret_key) = provider.credentials();
+    s3_bucket(provider, access_key, secret_key)
+}
+
+/// The provider's bucket, accessed with the given credentials.
+fn s3_bucket(provider: &ProviderConfig, access_key: &str, secret_key: &str) -> Result<Box<Bucket>> {
+    let region = Region::Custom {
+        region: provider.region().to_string(),
+        endpoint: provider.endpoint(),
+    };
+    let credentials = Credentials::new(Some(access_key), Some(secret_key), None, None, None)
+        .context("Failed to create S3 credentials")?;
+
+    Ok(Bucket::new(provider.bucket(), region, credentials)
+        .context("Failed to create bucket reference")?
+        .with_path_style())
+}
+
+/// Names of the buckets the provider's root credentials can see in its region or zone.
+async fn list_bucket_names(provider: &ProviderConfig) -> Result<Vec<String>> {
+    let region = Region::Custom {
+        region: provider.region().to_string(),
+        endpoint: provider.endpoint(),
+    };
+    let (access_key, secret_key) = provider.credentials();
+    let credentials = Credentials::new(Some(access_key), Some(secret_key), None, None, None)
+        .context("Failed to create S3 credentials")?;
+
+    let response = Bucket::list_buckets(region, credentials)
+        .await
+        .context("Failed to list buckets")?;
+    let mut names: Vec<String> = response.bucket_names().collect();
+    names.sort();
+    Ok(names)
+}
+
+/// List every object under `prefix`, following pagination.
+async fn list_prefix_objects(bucket: &Bucket, prefix: &str) -> Result<Vec<s3::serde_types::Object>> {
+    let pages = bucket
+        .list(prefix.to_string(), None)
+        .await
+        .with_context(|| format!("Failed to list objects under {}", prefix))?;
+
+    Ok(pages
+        .into_iter()
+        .flat_map(|page| page.contents)
+        .filter(|object| object.key.len() > prefix.len() && object.key.starts_with(prefix))
+        .collect())
+}
+
+/// Copy one object between buckets through a temporary file, keeping its content
+/// type. The data is checked against the source ETag and the uploaded copy's ETag
+/// whenever those are plain MD5 sums (multipart uploads are checked by size only).
+async fn copy_object(source: &Bucket, source_key: &str, target: &Bucket, target_key: &str) -> Result<()> {
+    use std::io::{Read, Seek};
+
+    let (head, status) = source
+        .head_object(source_key)
+        .await
+        .with_context(|| format!("Failed to read metadata of '{}'", source_key))?;
+    if status != 200 {
+        anyhow::bail!("Failed to read metadata of '{}' (status: {})", source_key, status);
+    }
+
+    let mut temp_file = tokio::fs::File::from_std(tempfile::tempfile()?);
+    let status = source
+        .get_object_to_writer(source_key, &mut temp_file)
+        .await
+        .with_context(|| format!("Failed to download '{}'", source_key))?;
+    if status != 200 {
+        anyhow::bail!("Failed to download '{}' (status: {})", source_key, status);
+    }
+    let mut temp_file = temp_file.into_std().await;
+    let size = temp_file.stream_position()?;
+    temp_file.rewind()?;
+
+    let mut md5 = md5::Context::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = temp_file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        md5.consume(&buffer[..read]);
+    }
+    let md5 = format!("{:x}", md5.compute());
+    temp_file.rewind()?;
+
+    if let Some(expected) = head.e_tag.as_deref().and_then(plain_md5) {
+        if expected != md5 {
+            anyhow::bail!("Checksum mismatch downloading '{}': expected {}, got {}", source_key, expected, md5);
+        }
+    }
+
+    let content_type = head.content_type.as_deref().unwrap_or("application/octet-stream");
+    let response = target
+        .put_object_stream_with_content_type(&mut tokio::fs::File::from_std(temp_file), target_key, content_type)
+        .await
+        .with_context(|| format!("Failed to upload '{}'", target_key))?;
+    if response.status_code() != 200 {
+        anyhow::bail!("Failed to upload '{}' (status: {})", target_key, response.status_code());
+    }
+
+    let (copy, status) = target
+        .head_object(target_key)
+        .await
+        .with_context(|| format!("Failed to read metadata of '{}'", target_key))?;
+    if status != 200 || copy.content_length != Some(size as i64) {
+        anyhow::bail!("Copy of '{}' has the wrong size", target_key);
+    }
+    if let Some(actual) = copy.e_tag.as_deref().and_then(plain_md5) {
+        if actual != md5 {
+            anyhow::bail!("Checksum mismatch uploading '{}': expected {}, got {}", target_key, md5, actual);
+        }
+    }
+
+    Ok(())
+}
+
+/// The MD5 sum in an ETag, unless the ETag belongs to a multipart upload.
+fn plain_md5(etag: &str) -> Option<String> {
+    let etag = etag.trim_matches('"');
+    (etag.len() == 32 && etag.chars().all(|c| c.is_ascii_hexdigit())).then(|| etag.to_lowercase())
+}
+
+/// Replace an app's bucket-policy statements with ones matching its record:
+/// `proprion-<app>` for its own prefixes, `proprion-<app>-from-<other>` per grant
+/// and `proprion-<app>-list` for listing.
+async fn apply_scaleway_bucket_policy(cfg: &ScalewayProviderConfig, app_name: &str, app: &AppConfig) -> Result<()> {
+    let new_statements = scaleway_bucket_statements(cfg, app_name, app);
+
+    edit_scaleway_bucket_policy(cfg, |statements| {
+        statements.retain(|s| !belongs_to_app(s, app_name, &app.id));
+        statements.extend(new_statements);
+    })
+    .await
+}
+
+/// The bucket-policy statements proprion generates for an app: one for its own
+/// folders, one per grant, and one allowing it to list the folders it can read.
+fn scaleway_bucket_statements(cfg: &ScalewayProviderConfig, app_name: &str, app: &AppConfig) -> Vec<serde_json::Value> {
+    let own_prefixes: Vec<String> = std::iter::once(&app.prefix)
+        .chain(&app.extra_prefixes)
+        .cloned()
+        .collect();
+
+    let mut statements = Vec::new();
+    if let Some(access) = app.effective_access(app.access) {
+        statements.push(bucket_policy_statement(
+            cfg,
+            &format!("proprion-{}", app_name),
+            &app.id,
+            &own_prefixes,
+            &access.bucket_policy_actions(),
+        ));
+    }
+    for grant in &app.grants {
+        let Some(access) = app.effective_access(grant.access) else {
+            continue;
+        };
+        statements.push(bucket_policy_statement(
+            cfg,
+            &format!("proprion-{}-from-{}", app_name, grant.from),
+            &app.id,
+            std::slice::from_ref(&grant.prefix),
+            &access.bucket_policy_actions(),
+        ));
+    }
+
+    // Listing is a bucket action, limited to the readable folders by the listing's prefix
+    let listable: Vec<String> = app
+        .scopes()
+        .into_iter()
+        .filter(|(_, access)| access.can_read())
+        .map(|(prefix, _)| format!("{}*", prefix))
+        .collect();
+    if !listable.is_empty() {
+        statements.push(serde_json::json!({
+            "Sid": format!("proprion-{}-list", app_name),
+            "Effect": "Allow",
+            "Principal": { "SCW": format!("application_id:{}", app.id) },
+            "Action": ["s3:ListBucket"],
+            "Resource": [cfg.bucket],
+            "Condition": { "StringLike": { "s3:prefix": listable } }
+        }));
+    }
+    statements
+}
+
+/// A bucket-policy statement allowing an application `actions` under `prefixes`.
+fn bucket_policy_statement(
+    cfg: &ScalewayProviderConfig,
+    sid: &str,
     application_id: &str,
-    app_name: &str,
-    app_prefix: &str,
+    prefixes: &[String],
+    actions: &[&str],
+) -> serde_json::Value {
+    let resources: Vec<String> = prefixes
+        .iter()
+        .map(|prefix| format!("{}/{}*", cfg.bucket, prefix))
+        .collect();
+
+    serde_json::json!({
+        "Sid": sid,
+        "Effect": "Allow",
+        "Principal": { "SCW": format!("application_id:{}", application_id) },
+        "Action": actions,
+        "Resource": resources
+    })
+}
+
+/// Whether a bucket-policy Sid belongs to the given app. App names can't contain
+/// `-from-` or end in `-list` (see `validate_name`), so an app's own Sid never
+/// looks like another app's grant or listing statement.
+fn is_app_statement(sid: &str, app_name: &str) -> bool {
+    let own = format!("proprion-{}", app_name);
+    sid == own || sid == format!("{}-list", own) || sid.starts_with(&format!("{}-from-", own))
+}
+
+/// Whether a bucket-policy statement belongs to the given app, by its Sid or by the
+/// application it grants access to (statements written by hand before `adopt`).
+fn belongs_to_app(statement: &serde_json::Value, app_name: &str, application_id: &str) -> bool {
+    statement_sid(statement).is_some_and(|sid| is_app_statement(sid, app_name))
+        || statement_application_id(statement) == Some(application_id)
+}
+
+/// Fetch the bucket policy, let `edit` change its statements, and apply the result.
+///
+/// The bucket policy API is not available through rust-s3, so this goes through
+/// the aws CLI. An edit that leaves no statements deletes the policy.
+async fn edit_scaleway_bucket_policy(
+    cfg: &ScalewayProviderConfig,
+    edit: impl FnOnce(&mut Vec<serde_json::Value>),
 ) -> Result<()> {
     use std::io::Write;
     use std::process::Command;
 
-    let endpoint = format!("https://s3.{}.scw.cloud", region);
-    let aws_env = [
-        ("AWS_ACCESS_KEY_ID", access_key),
-        ("AWS_SECRET_ACCESS_KEY", secret_key),
-    ];
+    let bucket = cfg.bucket.as_str();
+    let endpoint = cfg.endpoint();
+    let aws_env = s3_env(&cfg.access_key, &cfg.secret_key, &endpoint, &cfg.region);
 
-    // Get existing policy
-    let get_output = Command::new("aws")
-        .args(["s3api", "get-bucket-policy", "--bucket", bucket, "--endpoint-url", &endpoint, "--output", "json"])
-        .envs(aws_env.clone())
-        .output()
-        .context("Failed to execute aws CLI")?;
+    let mut policy = get_scaleway_bucket_policy(cfg)?;
 
-    let mut policy: serde_json::Value = if get_output.status.success() {
-        let output_str = String::from_utf8_lossy(&get_output.stdout);
-        let wrapper: serde_json::Value = serde_json::from_str(&output_str).unwrap_or_else(|_| serde_json::json!({}));
-        if let Some(policy_str) = wrapper.get("Policy").and_then(|p| p.as_str()) {
-            serde_json::from_str(policy_str).unwrap_or_else(|_| create_empty_policy())
-        } else {
-            create_empty_policy()
-        }
-    } else {
-        create_empty_policy()
-    };
+    if !policy.get("Statement").is_some_and(|s| s.is_array()) {
+        policy["Statement"] = serde_json::json!([]);
+    }
+    let statements = policy["Statement"]
+        .as_array_mut()
+        .context("Bucket policy has no statement list")?;
+    edit(statements);
 
-    // Add new statement
-    let new_statement = serde_json::json!({
-        "Sid": format!("proprion-{}", app_name),
-        "Effect": "Allow",
-        "Principal": { "SCW": format!("application_id:{}", application_id) },
-        "Action": ["s3:GetObject", "s3:PutObject", "s3:DeleteObject"],
-        "Resource": format!("{}/{}/*", bucket, app_prefix)
-    });
+    if statements.is_empty() {
+        let delete_output = Command::new("aws")
+            .args(["s3api", "delete-bucket-policy", "--bucket", bucket, "--endpoint-url", &endpoint])
+            .envs(aws_env)
+            .output()
+            .context("Failed to execute aws CLI")?;
 
-    if let Some(statements) = policy.get_mut("Statement") {
-        if let Some(arr) = statements.as_array_mut() {
-            arr.retain(|s| {
-                s.get("Sid")
-                    .and_then(|sid| sid.as_str())
-                    .map(|sid| sid != format!("proprion-{}", app_name))
-                    .unwrap_or(true)
-            });
-            arr.push(new_statement);
+        if !delete_output.status.success() {
+            let stderr = String::from_utf8_lossy(&delete_output.stderr);
+            anyhow::bail!("Failed to delete bucket policy: {}", stderr);
         }
+        return Ok(());
     }
 
     // Write and apply
@@ -669,9 +6262,276 @@ async fn apply_scaleway_bucket_policy(
     Ok(())
 }
 
+/// Fetch the current bucket policy, or an empty one if the bucket has none.
+fn get_scaleway_bucket_policy(cfg: &ScalewayProviderConfig) -> Result<serde_json::Value> {
+    use std::process::Command;
+
+    let endpoint = cfg.endpoint();
+    let get_output = Command::new("aws")
+        .args(["s3api", "get-bucket-policy", "--bucket", &cfg.bucket, "--endpoint-url", &endpoint, "--output", "json"])
+        .envs(s3_env(&cfg.access_key, &cfg.secret_key, &endpoint, &cfg.region))
+        .output()
+        .context("Failed to execute aws CLI")?;
+
+    if !get_output.status.success() {
+        let stderr = String::from_utf8_lossy(&get_output.stderr);
+        // Only a bucket without a policy is empty; anything else must not be mistaken for one
+        if stderr.contains("NoSuchBucketPolicy") {
+            return Ok(create_empty_policy());
+        }
+        anyhow::bail!("Failed to read bucket policy: {}", stderr.trim());
+    }
+
+    let output_str = String::from_utf8_lossy(&get_output.stdout);
+    let wrapper: serde_json::Value = serde_json::from_str(&output_str).context("Failed to parse aws CLI output")?;
+    let policy_str = wrapper
+        .get("Policy")
+        .and_then(|p| p.as_str())
+        .context("aws CLI output has no bucket policy")?;
+    serde_json::from_str(policy_str).context("Failed to parse bucket policy")
+}
+
+/// The Sid of a bucket-policy statement, if it has one.
+fn statement_sid(statement: &serde_json::Value) -> Option<&str> {
+    statement.get("Sid").and_then(|sid| sid.as_str())
+}
+
+/// The Scaleway application a bucket-policy statement grants access to, if any.
+fn statement_application_id(statement: &serde_json::Value) -> Option<&str> {
+    statement
+        .get("Principal")?
+        .get("SCW")?
+        .as_str()?
+        .strip_prefix("application_id:")
+}
+
+/// Environment variables understood by the aws CLI and most S3 SDKs.
+fn s3_env(access_key: &str, secret_key: &str, endpoint: &str, region: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("AWS_ACCESS_KEY_ID", access_key.to_string()),
+        ("AWS_SECRET_ACCESS_KEY", secret_key.to_string()),
+        ("AWS_ENDPOINT_URL", endpoint.to_string()),
+        ("AWS_REGION", region.to_string()),
+    ]
+}
+
+/// Run an `aws s3api` command on the provider's bucket with its root credentials
+/// and parse the JSON output. Commands that print nothing give an empty object.
+fn s3api(provider: &ProviderConfig, args: &[&str]) -> Result<serde_json::Value> {
+    use std::process::Command;
+
+    let endpoint = provider.endpoint();
+    let (access_key, secret_key) = provider.credentials();
+    let output = Command::new("aws")
+        .arg("s3api")
+        .args(args)
+        .args(["--bucket", provider.bucket(), "--endpoint-url", &endpoint, "--output", "json"])
+        .envs(s3_env(access_key, secret_key, &endpoint, provider.region()))
+        .output()
+        .context("Failed to execute aws CLI")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("aws s3api {} failed: {}", args.first().unwrap_or(&""), stderr.trim());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(&stdout).context("Failed to parse aws CLI output")
+}
+
+/// Run `command` with the app's credentials and scope in its environment.
+///
+/// On Unix the current process is replaced by the command, so its exit code
+/// and any terminating signal are seen directly by the caller. Elsewhere the
+/// command is waited on and its exit code forwarded.
+fn exec_with_app_env(app: &AppConfig, key: &AppKey, provider: &ProviderConfig, command: &[String]) -> Result<()> {
+    use std::process::Command;
+
+    let (program, args) = command.split_first().context("No command given")?;
+
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .envs(s3_env(&key.access_key, &key.secret_key, &provider.endpoint(), provider.region()))
+        .env("PROPRION_BUCKET", provider.bucket())
+        .env("PROPRION_PREFIX", &app.prefix);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // exec only returns on failure
+        let err = cmd.exec();
+        Err(err).with_context(|| format!("Failed to execute '{}'", program))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = cmd
+            .status()
+            .with_context(|| format!("Failed to execute '{}'", program))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
 fn create_empty_policy() -> serde_json::Value {
     serde_json::json!({
         "Version": "2023-04-17",
         "Statement": []
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90s").unwrap(), 90);
+        assert_eq!(parse_duration("30m").unwrap(), 30 * 60);
+        assert_eq!(parse_duration("24h").unwrap(), 24 * 3600);
+        assert_eq!(parse_duration(" 7d ").unwrap(), 7 * 86400);
+        assert_eq!(parse_duration("2w").unwrap(), 14 * 86400);
+    }
+
+    #[test]
+    fn parse_duration_rejects_bad_input() {
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("7y").is_err());
+        assert!(parse_duration("-1d").is_err());
+        assert!(parse_duration("99999999999999999w").is_err());
+        assert!(parse_duration("36501d").is_err());
+    }
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10B").unwrap(), 10);
+        assert_eq!(parse_size("500MB").unwrap(), 500_000_000);
+        assert_eq!(parse_size("10GiB").unwrap(), 10 << 30);
+        assert_eq!(parse_size("1.5TB").unwrap(), 1_500_000_000_000);
+        assert_eq!(parse_size("2 kib").unwrap(), 2048);
+        assert!(parse_size("10XB").is_err());
+        assert!(parse_size("GB").is_err());
+    }
+
+    #[test]
+    fn normalize_prefix_adds_trailing_slash() {
+        assert_eq!(normalize_prefix("shared/plans").unwrap(), "shared/plans/");
+        assert_eq!(normalize_prefix(" shared/ ").unwrap(), "shared/");
+        assert!(normalize_prefix("").is_err());
+        assert!(normalize_prefix("/absolute").is_err());
+        assert!(normalize_prefix("it's").is_err());
+    }
+
+    #[test]
+    fn validate_name_rules() {
+        assert!(validate_name("app name", "photo-sync_2").is_ok());
+        assert!(validate_name("app name", "").is_err());
+        assert!(validate_name("app name", "has space").is_err());
+        assert!(validate_name("app name", "a/b").is_err());
+        assert!(validate_name("app name", "a-from-b").is_err());
+        assert!(validate_name("app name", "backup-list").is_err());
+    }
+
+    #[test]
+    fn app_statements_are_told_apart() {
+        assert!(is_app_statement("proprion-a", "a"));
+        assert!(is_app_statement("proprion-a-list", "a"));
+        assert!(is_app_statement("proprion-a-from-b", "a"));
+        assert!(!is_app_statement("proprion-ab", "a"));
+        assert!(!is_app_statement("proprion-b-from-a", "a"));
+        assert!(!is_app_statement("proprion-a-b", "a"));
+        assert!(!is_app_statement("other", "a"));
+    }
+
+    #[test]
+    fn quoted_after_finds_first_quoted_string() {
+        let expression = "operation == 'get-object' && parameters.key.startsWith('apps/a/')";
+        assert_eq!(quoted_after(expression, "operation =="), Some("get-object"));
+        assert_eq!(quoted_after(expression, "startsWith("), Some("apps/a/"));
+        assert_eq!(quoted_after(expression, "resources.bucket =="), None);
+        assert_eq!(quoted_after("startsWith('unterminated", "startsWith("), None);
+    }
+
+    #[test]
+    fn rule_operations_reads_lists_and_single_operations() {
+        assert_eq!(
+            rule_operations("operation in ['get-object', 'put-object'] && resources.bucket == 'b'"),
+            Some(vec!["get-object", "put-object"])
+        );
+        assert_eq!(
+            rule_operations("operation == 'list-objects' && resources.bucket == 'b'"),
+            Some(vec!["list-objects"])
+        );
+        assert_eq!(rule_operations("resources.bucket == 'b'"), None);
+    }
+
+    fn observe_rules(expressions: &[&str]) -> Vec<String> {
+        let policy = exoscale::RolePolicy {
+            default_service_strategy: "deny".to_string(),
+            services: std::collections::BTreeMap::from([(
+                "sos".to_string(),
+                exoscale::ServicePolicy {
+                    policy_type: "rules".to_string(),
+                    rules: Some(
+                        expressions
+                            .iter()
+                            .map(|expression| exoscale::PolicyRule {
+                                action: "allow".to_string(),
+                                expression: expression.to_string(),
+                            })
+                            .collect(),
+                    ),
+                },
+            )]),
+        };
+        let mut warnings = Vec::new();
+        observe_role_policy("b", &policy, &mut warnings).unwrap();
+        warnings
+    }
+
+    #[test]
+    fn observe_role_policy_warns_about_bucket_wide_listing() {
+        let warnings = observe_rules(&[
+            "operation == 'list-objects' && resources.bucket == 'b'",
+            "operation in ['get-object'] && resources.bucket == 'b' && parameters.key.startsWith('apps/a/')",
+        ]);
+        assert_eq!(warnings, ["The role can list the whole bucket"]);
+    }
+
+    #[test]
+    fn observe_role_policy_accepts_proprion_policies() {
+        let scopes = exoscale_scopes(&[("apps/a/".to_string(), AccessMode::Full)]);
+        let policy = exoscale::sos_policy("b", &scopes);
+        let rules: Vec<String> = policy.services["sos"]
+            .rules
+            .iter()
+            .flatten()
+            .map(|rule| rule.expression.clone())
+            .collect();
+        let rules: Vec<&str> = rules.iter().map(String::as_str).collect();
+        assert!(observe_rules(&rules).is_empty());
+    }
+
+    #[test]
+    fn grants_need_an_active_source() {
+        let mut source = AppConfig::new("p", "id".to_string(), "", "apps/a/".to_string(), AccessMode::Full, None);
+        assert!(check_grant_source("a", &source).is_ok());
+        for status in [AppStatus::Suspended, AppStatus::Expired, AppStatus::Deleted] {
+            source.status = status;
+            let err = check_grant_source("a", &source).unwrap_err();
+            assert!(err.to_string().contains(&format!("App 'a' is {}", status)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn format_bytes_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(10 << 30), "10.0 GiB");
+    }
+}
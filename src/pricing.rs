@@ -0,0 +1,125 @@
+//! Bundled object storage price table used to estimate monthly costs.
+
+use crate::config::ProviderConfig;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+const PRICES_TOML: &str = include_str!("prices.toml");
+
+/// Bytes per GB as billed by the providers
+const GB: f64 = 1_000_000_000.0;
+
+/// Storage class of objects uploaded without one
+pub const DEFAULT_STORAGE_CLASS: &str = "STANDARD";
+
+/// List prices per provider type and region
+#[derive(Debug, Deserialize)]
+pub struct PriceTable {
+    /// Bumped whenever prices change
+    pub version: u32,
+    /// Month the prices were taken from the providers' pricing pages
+    pub as_of: String,
+    pub currency: String,
+    scaleway: HashMap<String, RegionPrices>,
+    exoscale: HashMap<String, RegionPrices>,
+}
+
+/// Prices in one Scaleway region or Exoscale zone
+#[derive(Debug, Deserialize)]
+pub struct RegionPrices {
+    /// Price per GB-month, by storage class
+    storage_gb_month: BTreeMap<String, f64>,
+    /// Price per GB downloaded
+    egress_gb: f64,
+    /// GB of egress included each month
+    #[serde(default)]
+    free_egress_gb: f64,
+    /// Price per 1000 requests
+    #[serde(default)]
+    pub requests_per_1000: f64,
+}
+
+impl PriceTable {
+    /// The price table shipped with this build.
+    pub fn bundled() -> Self {
+        toml::from_str(PRICES_TOML).expect("bundled price table is valid")
+    }
+
+    /// Prices for a provider's region or zone, if known.
+    pub fn region(&self, provider: &ProviderConfig) -> Option<&RegionPrices> {
+        match provider {
+            ProviderConfig::Scaleway(cfg) => self.scaleway.get(&cfg.region),
+            ProviderConfig::Exoscale(cfg) => self.exoscale.get(&cfg.zone),
+        }
+    }
+}
+
+impl RegionPrices {
+    /// Monthly cost of storing the given bytes per storage class.
+    /// Classes the region doesn't offer are priced as STANDARD.
+    pub fn storage_cost(&self, bytes_by_class: &BTreeMap<String, u64>) -> f64 {
+        let standard = self.storage_gb_month.get(DEFAULT_STORAGE_CLASS).copied().unwrap_or(0.0);
+        bytes_by_class
+            .iter()
+            .map(|(class, bytes)| {
+                let price = self.storage_gb_month.get(class).copied().unwrap_or(standard);
+                *bytes as f64 / GB * price
+            })
+            .sum()
+    }
+
+    /// Monthly cost of downloading `bytes`, after the free allowance.
+    pub fn egress_cost(&self, bytes: u64) -> f64 {
+        (bytes as f64 / GB - self.free_egress_gb).max(0.0) * self.egress_gb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{EXOSCALE_ZONES, SCALEWAY_REGIONS};
+
+    #[test]
+    fn bundled_table_parses() {
+        let table = PriceTable::bundled();
+        assert!(table.version >= 1);
+        assert_eq!(table.currency, "EUR");
+    }
+
+    #[test]
+    fn bundled_table_covers_every_known_region() {
+        let table = PriceTable::bundled();
+        for region in SCALEWAY_REGIONS {
+            let prices = table.scaleway.get(*region).unwrap_or_else(|| panic!("no prices for {}", region));
+            assert!(prices.storage_gb_month.contains_key(DEFAULT_STORAGE_CLASS), "{}", region);
+        }
+        for zone in EXOSCALE_ZONES {
+            let prices = table.exoscale.get(*zone).unwrap_or_else(|| panic!("no prices for {}", zone));
+            assert!(prices.storage_gb_month.contains_key(DEFAULT_STORAGE_CLASS), "{}", zone);
+        }
+    }
+
+    #[test]
+    fn unknown_storage_classes_are_priced_as_standard() {
+        let prices = RegionPrices {
+            storage_gb_month: BTreeMap::from([(DEFAULT_STORAGE_CLASS.to_string(), 0.02)]),
+            egress_gb: 0.01,
+            free_egress_gb: 75.0,
+            requests_per_1000: 0.0,
+        };
+        let bytes = BTreeMap::from([("GLACIER".to_string(), 10 * GB as u64)]);
+        assert!((prices.storage_cost(&bytes) - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn egress_allowance_is_free() {
+        let prices = RegionPrices {
+            storage_gb_month: BTreeMap::new(),
+            egress_gb: 0.01,
+            free_egress_gb: 75.0,
+            requests_per_1000: 0.0,
+        };
+        assert_eq!(prices.egress_cost(50 * GB as u64), 0.0);
+        assert!((prices.egress_cost(175 * GB as u64) - 1.0).abs() < 1e-9);
+    }
+}
@@ -1,11 +1,19 @@
 //! Scaleway IAM API client for managing applications, policies, and API keys.
 
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 const IAM_API_BASE: &str = "https://api.scaleway.com/iam/v1alpha1";
 
+/// Tag put on every application and policy this client creates.
+/// API keys have no tags; they belong to a tagged application.
+pub const TAG: &str = "proprion";
+
+/// Items per page of list endpoints (the API maximum)
+const PAGE_SIZE: usize = 100;
+
 #[derive(Error, Debug)]
 pub enum ScalewayError {
     #[error("HTTP request failed: {0}")]
@@ -35,6 +43,15 @@ pub struct Application {
     pub description: Option<String>,
     pub created_at: Option<String>,
     pub organization_id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Application {
+    /// Whether the application was created by proprion.
+    pub fn is_tagged(&self) -> bool {
+        self.tags.iter().any(|tag| tag == TAG)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,6 +64,15 @@ pub struct Policy {
     pub id: String,
     pub name: String,
     pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Policy {
+    /// Whether the policy was created by proprion.
+    pub fn is_tagged(&self) -> bool {
+        self.tags.iter().any(|tag| tag == TAG)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,6 +80,20 @@ struct PoliciesResponse {
     policies: Vec<Policy>,
 }
 
+/// A rule of a policy: permission sets granted in some projects, or organization-wide
+#[derive(Debug, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub permission_set_names: Option<Vec<String>>,
+    #[serde(default)]
+    pub project_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesResponse {
+    rules: Vec<Rule>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ApiKey {
     pub access_key: String,
@@ -81,6 +121,7 @@ struct CreateApplicationRequest<'a> {
     name: &'a str,
     description: &'a str,
     organization_id: &'a str,
+    tags: Vec<&'a str>,
 }
 
 #[derive(Serialize)]
@@ -89,6 +130,13 @@ struct CreatePolicyRequest<'a> {
     organization_id: &'a str,
     application_id: &'a str,
     rules: Vec<PolicyRule<'a>>,
+    tags: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct SetRulesRequest<'a> {
+    policy_id: &'a str,
+    rules: Vec<PolicyRule<'a>>,
 }
 
 #[derive(Serialize)]
@@ -103,6 +151,8 @@ struct CreateApiKeyRequest<'a> {
     description: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
     default_project_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<&'a str>,
 }
 
 impl Client {
@@ -140,7 +190,24 @@ impl Client {
         }
     }
 
-    /// Create a new IAM application.
+    /// Fetch every page of a list endpoint. `url` must already have a query string.
+    async fn list_pages<R: DeserializeOwned, T>(&self, url: &str, items: impl Fn(R) -> Vec<T>) -> Result<Vec<T>> {
+        let mut all = Vec::new();
+        for page in 1.. {
+            let page_url = format!("{}&page={}&page_size={}", url, page, PAGE_SIZE);
+            let response = self.http.get(&page_url).headers(self.headers()).send().await?;
+            let response = self.check_response(response).await?;
+            let batch = items(response.json().await?);
+            let last = batch.len() < PAGE_SIZE;
+            all.extend(batch);
+            if last {
+                break;
+            }
+        }
+        Ok(all)
+    }
+
+    /// Create a new IAM application, tagged as proprion's.
     pub async fn create_application(
         &self,
         name: &str,
@@ -152,6 +219,7 @@ impl Client {
             name,
             description,
             organization_id,
+            tags: vec![TAG],
         };
 
         let response = self
@@ -167,18 +235,17 @@ impl Client {
         Ok(app)
     }
 
-    /// List all applications in an organization.
-    pub async fn list_applications(&self, organization_id: &str) -> Result<Vec<Application>> {
-        let url = format!(
+    /// List all applications in an organization, or only those with `tag`.
+    pub async fn list_applications(&self, organization_id: &str, tag: Option<&str>) -> Result<Vec<Application>> {
+        let mut url = format!(
             "{}/applications?organization_id={}",
             IAM_API_BASE, organization_id
         );
+        if let Some(tag) = tag {
+            url.push_str(&format!("&tag={}", tag));
+        }
 
-        let response = self.http.get(&url).headers(self.headers()).send().await?;
-
-        let response = self.check_response(response).await?;
-        let apps: ApplicationsResponse = response.json().await?;
-        Ok(apps.applications)
+        self.list_pages(&url, |apps: ApplicationsResponse| apps.applications).await
     }
 
     /// Delete an application.
@@ -196,8 +263,9 @@ impl Client {
         Ok(())
     }
 
-    /// Create a policy with scoped Object Storage permissions for a project.
-    /// Uses ObjectStorageObjectsRead, ObjectStorageObjectsWrite, ObjectStorageObjectsDelete
+    /// Create a policy with scoped Object Storage permissions for a project,
+    /// tagged as proprion's.
+    /// Callers pass object-level sets such as ObjectStorageObjectsRead
     /// instead of ObjectStorageFullAccess for better security.
     pub async fn create_policy(
         &self,
@@ -205,6 +273,7 @@ impl Client {
         application_id: &str,
         organization_id: &str,
         project_id: &str,
+        permission_set_names: &[&str],
     ) -> Result<Policy> {
         let url = format!("{}/policies", IAM_API_BASE);
         let payload = CreatePolicyRequest {
@@ -213,13 +282,9 @@ impl Client {
             application_id,
             rules: vec![PolicyRule {
                 project_ids: vec![project_id],
-                // Scoped permissions - only object operations, not bucket management
-                permission_set_names: vec![
-                    "ObjectStorageObjectsRead",
-                    "ObjectStorageObjectsWrite",
-                    "ObjectStorageObjectsDelete",
-                ],
+                permission_set_names: permission_set_names.to_vec(),
             }],
+            tags: vec![TAG],
         };
 
         let response = self
@@ -235,15 +300,49 @@ impl Client {
         Ok(policy)
     }
 
-    /// List policies for an application.
-    pub async fn list_policies(&self, application_id: &str) -> Result<Vec<Policy>> {
-        let url = format!("{}/policies?application_id={}", IAM_API_BASE, application_id);
+    /// List policies for an application, or only those with `tag`.
+    pub async fn list_policies(&self, application_id: &str, tag: Option<&str>) -> Result<Vec<Policy>> {
+        let mut url = format!("{}/policies?application_id={}", IAM_API_BASE, application_id);
+        if let Some(tag) = tag {
+            url.push_str(&format!("&tag={}", tag));
+        }
 
-        let response = self.http.get(&url).headers(self.headers()).send().await?;
+        self.list_pages(&url, |policies: PoliciesResponse| policies.policies).await
+    }
 
-        let response = self.check_response(response).await?;
-        let policies: PoliciesResponse = response.json().await?;
-        Ok(policies.policies)
+    /// List the rules of a policy.
+    pub async fn list_rules(&self, policy_id: &str) -> Result<Vec<Rule>> {
+        let url = format!("{}/rules?policy_id={}", IAM_API_BASE, policy_id);
+
+        self.list_pages(&url, |rules: RulesResponse| rules.rules).await
+    }
+
+    /// Replace the rules of a policy with a single rule for a project.
+    pub async fn set_policy_rules(
+        &self,
+        policy_id: &str,
+        project_id: &str,
+        permission_set_names: &[&str],
+    ) -> Result<()> {
+        let url = format!("{}/rules", IAM_API_BASE);
+        let payload = SetRulesRequest {
+            policy_id,
+            rules: vec![PolicyRule {
+                project_ids: vec![project_id],
+                permission_set_names: permission_set_names.to_vec(),
+            }],
+        };
+
+        let response = self
+            .http
+            .put(&url)
+            .headers(self.headers())
+            .json(&payload)
+            .send()
+            .await?;
+
+        self.check_response(response).await?;
+        Ok(())
     }
 
     /// Delete a policy.
@@ -262,17 +361,20 @@ impl Client {
     }
 
     /// Create an API key for an application.
+    /// `expires_at` is an RFC 3339 timestamp after which Scaleway rejects the key.
     pub async fn create_api_key(
         &self,
         application_id: &str,
         description: &str,
         default_project_id: Option<&str>,
+        expires_at: Option<&str>,
     ) -> Result<ApiKey> {
         let url = format!("{}/api-keys", IAM_API_BASE);
         let payload = CreateApiKeyRequest {
             application_id,
             description,
             default_project_id,
+            expires_at,
         };
 
         let response = self
@@ -292,11 +394,7 @@ impl Client {
     pub async fn list_api_keys(&self, application_id: &str) -> Result<Vec<ApiKey>> {
         let url = format!("{}/api-keys?application_id={}", IAM_API_BASE, application_id);
 
-        let response = self.http.get(&url).headers(self.headers()).send().await?;
-
-        let response = self.check_response(response).await?;
-        let keys: ApiKeysResponse = response.json().await?;
-        Ok(keys.api_keys)
+        self.list_pages(&url, |keys: ApiKeysResponse| keys.api_keys).await
     }
 
     /// Delete an API key.